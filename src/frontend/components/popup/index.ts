@@ -1,16 +1,29 @@
 // 類型定義
 export type {
+  DiffFile,
+  FileReview,
+  FormField,
   ImageData,
   IPopupManager,
   McpRequest,
+  PopupAttachment,
   PopupConfig,
+  PopupQuestion,
+  PredefinedOption,
+  QuestionAnswer,
   PopupEvent,
   PopupResponse,
   PopupState,
 } from '../../types/popup'
 // 弹窗元件匯出
 export { default as McpPopup } from './McpPopup.vue'
+export { default as PopupDiffReview } from './PopupDiffReview.vue'
 export { default as PopupActions } from './PopupActions.vue'
+export { default as PopupAttachments } from './PopupAttachments.vue'
 export { default as PopupContent } from './PopupContent.vue'
+export { default as PopupForm } from './PopupForm.vue'
 export { default as PopupHeader } from './PopupHeader.vue'
 export { default as PopupInput } from './PopupInput.vue'
+export { default as PopupOptionList } from './PopupOptionList.vue'
+export { default as PopupQuestions } from './PopupQuestions.vue'
+export { default as PopupQueueTabs } from './PopupQueueTabs.vue'
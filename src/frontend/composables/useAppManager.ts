@@ -62,6 +62,7 @@ export function useAppManager() {
     mcp: {
       handleResponse: mcpHandler.handleMcpResponse,
       handleCancel: mcpHandler.handleMcpCancel,
+      selectRequest: mcpHandler.selectRequest,
     },
     // 音訊操作
     audio: {
@@ -82,6 +83,7 @@ export function useAppManager() {
     // 直接解构狀態，Vue模板会自動處理響應式
     naiveTheme: theme.naiveTheme,
     mcpRequest: mcpHandler.mcpRequest,
+    pendingRequests: mcpHandler.pendingRequests,
     showMcpPopup: mcpHandler.showMcpPopup,
     appConfig,
     isInitializing: appInit.isInitializing,
@@ -7,6 +7,7 @@ export function useEventHandlers(actions: any) {
     // MCP 事件
     onMcpResponse: actions.mcp.handleResponse,
     onMcpCancel: actions.mcp.handleCancel,
+    onSelectRequest: actions.mcp.selectRequest,
 
     // 主題事件
     onThemeChange: actions.theme.setTheme,
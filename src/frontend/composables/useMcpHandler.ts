@@ -1,21 +1,50 @@
 import { invoke } from '@tauri-apps/api/core'
 import { listen } from '@tauri-apps/api/event'
-import { ref } from 'vue'
+import { computed, ref } from 'vue'
 
 /**
  * MCP處理組合式函數
  */
 export function useMcpHandler() {
-  const mcpRequest = ref(null)
+  // 常駐模式下可能同時有多個請求，依到達順序排隊並以分頁顯示
+  const pendingRequests = ref<any[]>([])
+  const activeRequestId = ref<string | null>(null)
+  const mcpRequest = computed(() =>
+    pendingRequests.value.find(request => request.id === activeRequestId.value)
+    ?? pendingRequests.value[0]
+    ?? null,
+  )
   const showMcpPopup = ref(false)
 
+  /**
+   * 切換目前顯示的請求
+   */
+  function selectRequest(requestId: string) {
+    activeRequestId.value = requestId
+  }
+
+  /**
+   * 從佇列移除請求，佇列清空時收起弹窗
+   */
+  function removeRequest(requestId?: string | null) {
+    if (!requestId)
+      return
+    pendingRequests.value = pendingRequests.value.filter(request => request.id !== requestId)
+    if (activeRequestId.value === requestId)
+      activeRequestId.value = pendingRequests.value[0]?.id ?? null
+    if (pendingRequests.value.length === 0)
+      showMcpPopup.value = false
+  }
+
   /**
    * 統一的MCP回應處理
    */
   async function handleMcpResponse(response: any) {
+    const requestId = response?.metadata?.request_id ?? mcpRequest.value?.id ?? null
     try {
-      // 透過Tauri命令傳送回應并退出應用
-      await invoke('send_mcp_response', { response })
+      // 透過Tauri命令依請求 ID 傳送回應并退出應用
+      await invoke('send_mcp_response', { response, requestId })
+      removeRequest(requestId)
       await invoke('exit_app')
     }
     catch (error) {
@@ -27,9 +56,11 @@ export function useMcpHandler() {
    * 統一的MCP取消處理
    */
   async function handleMcpCancel() {
+    const requestId = mcpRequest.value?.id ?? null
     try {
       // 傳送取消訊息并退出應用
-      await invoke('send_mcp_response', { response: 'CANCELLED' })
+      await invoke('send_mcp_response', { response: 'CANCELLED', requestId })
+      removeRequest(requestId)
       await invoke('exit_app')
     }
     catch (error) {
@@ -59,8 +90,11 @@ export function useMcpHandler() {
 
     // 根据設定决定是否顯示前端弹窗
     if (shouldShowFrontendPopup) {
-      // 設定請求資料和顯示狀態
-      mcpRequest.value = request
+      // 加入佇列；目前沒有顯示中的請求時直接切換到新請求
+      if (!pendingRequests.value.some(pending => pending.id === request?.id))
+        pendingRequests.value.push(request)
+      if (!activeRequestId.value || !showMcpPopup.value)
+        activeRequestId.value = request?.id ?? null
       showMcpPopup.value = true
     }
     else {
@@ -122,6 +156,18 @@ export function useMcpHandler() {
       await listen('mcp-request', (event) => {
         showMcpDialog(event.payload)
       })
+
+      // 常駐模式：請求已回應或被寸止取消，關閉對應分頁
+      await listen<string | null>('mcp-request-closed', (event) => {
+        if (event.payload) {
+          removeRequest(event.payload)
+        }
+        else {
+          pendingRequests.value = []
+          activeRequestId.value = null
+          showMcpPopup.value = false
+        }
+      })
     }
     catch (error) {
       console.error('設定MCP事件監聽器失敗:', error)
@@ -130,7 +176,10 @@ export function useMcpHandler() {
 
   return {
     mcpRequest,
+    pendingRequests,
+    activeRequestId,
     showMcpPopup,
+    selectRequest,
     handleMcpResponse,
     handleMcpCancel,
     showMcpDialog,
@@ -0,0 +1,53 @@
+import type { McpRequest, PredefinedOption } from '../types/popup'
+import { computed } from 'vue'
+
+/**
+ * 將預定義選項正規化為物件，純文字選項的 id 与 label 相同
+ */
+export function normalizeOptions(options?: Array<string | PredefinedOption>): PredefinedOption[] {
+  return (options ?? []).map(option =>
+    typeof option === 'string' ? { id: option, label: option } : option,
+  )
+}
+
+// 帶有預定義選項的請求或問題
+export type OptionSource = Pick<McpRequest, 'predefined_options' | 'selection_mode'>
+
+/**
+ * 弹窗預定義選項組合式函數
+ *
+ * 選擇狀態以選項 id 保存，並依 selection_mode 与 exclusive 限制選擇
+ */
+export function usePopupOptions(request: () => OptionSource | null | undefined) {
+  const options = computed(() => normalizeOptions(request()?.predefined_options))
+  const isSingle = computed(() => request()?.selection_mode === 'single')
+
+  /**
+   * 切換選項，傳回新的已選 id 列表
+   */
+  function toggleOption(selectedIds: string[], id: string): string[] {
+    if (selectedIds.includes(id))
+      return selectedIds.filter(selected => selected !== id)
+
+    const option = options.value.find(item => item.id === id)
+    // 單選或互斥選項：只保留目前選項
+    if (isSingle.value || option?.exclusive)
+      return [id]
+    // 選擇一般選項時取消已選的互斥選項
+    return [...selectedIds.filter(selected => !options.value.find(item => item.id === selected)?.exclusive), id]
+  }
+
+  /**
+   * 依 id 取得選項文字
+   */
+  function labelsOf(selectedIds: string[]): string[] {
+    return selectedIds.map(id => options.value.find(option => option.id === id)?.label ?? id)
+  }
+
+  return {
+    options,
+    isSingle,
+    toggleOption,
+    labelsOf,
+  }
+}
@@ -38,8 +38,12 @@ export const PROMPT_SECTIONS = {
     base: ``,
     detail: `記憶管理使用细節：
 - 對話開始時查詢 \`回忆\` 參數 \`project_path\` 为 git 的根目录
+- 記憶較多時，\`回忆\` 帶上 query（可加 category、limit）只取回与目前任務相關的記憶
 - 当发现用户輸入"請记住："時，要对用户的消息進行總結后呼叫 \`記憶\` 的 add 功能新增記憶
 - 使用 \`記憶\` 的 add 功能新增新記憶（content + category: rule/preference/pattern/context）
+- 与專案無關的個人偏好（例如回答語言、慣用工具）以 scope: global 新增，所有專案共用
+- 新增時若回報已有相近的記憶，改用 update 修改該 ID，不要換個說法再新增一次
+- 記憶過時或有誤時，先以 list 取得記憶 ID，再用 update / delete / move-category 修正，不要新增互相矛盾的記憶
 - 僅在重要變更時更新記憶，保持简洁`,
   } as PromptSection,
 }
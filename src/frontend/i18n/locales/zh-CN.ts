@@ -20,6 +20,7 @@ export default {
     intro: '介紹',
     mcpTools: 'MCP 工具',
     prompts: '參考提示詞',
+    notifications: '通知記錄',
     settings: '設定',
   },
 
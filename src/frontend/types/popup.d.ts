@@ -3,8 +3,111 @@
 export interface McpRequest {
   id: string
   message: string
-  predefined_options?: string[]
+  predefined_options?: Array<string | PredefinedOption>
+  selection_mode?: 'single' | 'multiple' // 選擇模式，預設為多選
   is_markdown?: boolean
+  timeout_seconds?: number // 逾時秒數，到期後由 MCP 伺服器關閉弹窗
+  default_option?: string // 逾時時自動選擇的選項 id
+  client_name?: string // 發出請求的 MCP 客戶端
+  client_version?: string // MCP 客戶端版本
+  project_name?: string // 請求所屬的專案
+  project_path?: string // 專案路徑（MCP root 或工作目錄）
+  git_branch?: string // 專案目前的 git 分支
+  questions?: PopupQuestion[] // 在同一個視窗中逐一回答的問題
+  diff_files?: DiffFile[] // 需要逐區塊審查的差異
+  form_fields?: FormField[] // zhi_form 的表單欄位
+  attachments?: PopupAttachment[] // AI 提供的圖片与文字附件
+}
+
+export interface PopupAttachment {
+  name: string
+  media_type: string
+  kind: 'image' | 'text'
+  data: string // 圖片為 base64，文字為檔案內容
+  size: number
+  path?: string // 讀取的本機檔案
+}
+
+export type FormFieldKind = 'string' | 'number' | 'integer' | 'boolean' | 'array'
+
+// 表單欄位，由 MCP 伺服器依 JSON Schema 產生
+export interface FormField {
+  name: string
+  title: string
+  description?: string
+  kind: FormFieldKind
+  item_kind?: FormFieldKind // 陣列元素類型
+  required: boolean
+  default?: unknown
+  options?: FormOption[] // 列舉的可選值
+  minimum?: number
+  maximum?: number
+  min_length?: number
+  max_length?: number
+  pattern?: string
+  min_items?: number
+  max_items?: number
+}
+
+// 列舉欄位的可選值
+export interface FormOption {
+  value: string | number | boolean
+  label: string
+}
+
+// 差異中的單一檔案
+export interface DiffFile {
+  path: string
+  old_path?: string // 重新命名前的路徑
+  hunks: DiffHunk[]
+}
+
+// 差異區塊，lines 保留 ' '、'+'、'-' 前綴
+export interface DiffHunk {
+  header: string
+  lines: string[]
+}
+
+export type ReviewVerdict = 'approved' | 'rejected' | 'commented' | 'pending'
+
+// 單一區塊的審查結果
+export interface HunkReview {
+  index: number // 從 1 開始
+  header: string
+  verdict: ReviewVerdict
+  comment: string | null
+}
+
+// 單一檔案的審查結果
+export interface FileReview {
+  path: string
+  comment: string | null
+  hunks: HunkReview[]
+}
+
+// 批次詢問中的單一問題
+export interface PopupQuestion {
+  id: string
+  message: string
+  predefined_options?: Array<string | PredefinedOption>
+  selection_mode?: 'single' | 'multiple'
+}
+
+// 單一問題的答案
+export interface QuestionAnswer {
+  question_id: string
+  selected_options: string[]
+  selected_option_ids: string[]
+  user_input: string | null
+}
+
+// 預定義選項，id 用於回應，label 顯示給使用者
+export interface PredefinedOption {
+  id: string
+  label: string
+  description?: string
+  recommended?: boolean // 建議選項
+  exclusive?: boolean // 互斥選項，選擇時取消其他選擇
 }
 
 // 自訂prompt類型定義
@@ -66,10 +169,39 @@ export interface ShortcutKey {
 export interface McpResponse {
   user_input: string | null
   selected_options: string[]
+  selected_option_ids?: string[]
   images: ImageAttachment[]
+  answers?: QuestionAnswer[]
+  review?: FileReview[]
+  form_values?: Record<string, unknown> // zhi_form 的表單值
   metadata: ResponseMetadata
 }
 
+// zhi_notify 的進度通知
+export type NotifyLevel = 'info' | 'success' | 'warning' | 'error'
+
+export interface McpNotification {
+  id: string
+  session_id: string
+  title?: string
+  message: string
+  step?: number
+  total?: number
+  level: NotifyLevel
+  play_sound: boolean
+  client_name?: string
+  project_name?: string
+  created_at: string
+}
+
+export interface NotificationSession {
+  session_id: string
+  client_name?: string
+  project_name?: string
+  updated_at: string
+  notifications: McpNotification[] // 由舊到新
+}
+
 export interface ImageAttachment {
   data: string
   media_type: string
@@ -80,6 +212,7 @@ export interface ResponseMetadata {
   timestamp: string | null
   request_id: string | null
   source: string | null
+  timed_out?: boolean
 }
 
 // 旧格式相容性支持
@@ -73,6 +73,8 @@ pub fn build_tauri_app() -> Builder<tauri::Wry> {
             build_mcp_send_response,
             build_mcp_continue_response,
             create_test_popup,
+            get_notification_log,
+            clear_notification_log,
 
             // 自訂prompt命令
             get_custom_prompt_config,
@@ -1,4 +1,5 @@
 use crate::app::builder::run_tauri_app;
+use crate::app::daemon::DAEMON_ARG;
 use anyhow::Result;
 
 /// 處理命令列參數
@@ -15,6 +16,7 @@ pub fn handle_cli_args() -> Result<()> {
             match args[1].as_str() {
                 "--help" | "-h" => print_help(),
                 "--version" | "-v" => print_version(),
+                DAEMON_ARG => run_tauri_app(),
                 _ => {
                     eprintln!("未知參數: {}", args[1]);
                     print_help();
@@ -51,6 +53,7 @@ fn print_help() {
     println!("用法:");
     println!("  等一下                    啟動設定介面");
     println!("  等一下 --mcp-request <檔案>  處理 MCP 請求");
+    println!("  等一下 --daemon           常駐於系統匣，透過本機 IPC 接收寸止的弹窗請求");
     println!("  等一下 --help             顯示此幫助訊息");
     println!("  等一下 --version          顯示版本訊息");
 }
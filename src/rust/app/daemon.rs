@@ -0,0 +1,310 @@
+use crate::config::AppState;
+use crate::ui::audio::play_audio_file;
+use crate::mcp::types::{Notification, PopupRequest};
+#[cfg(unix)]
+use crate::utils::ipc::verify_peer;
+use crate::utils::ipc::{daemon_endpoint, read_message, write_message, DaemonMessage, DaemonReply};
+use crate::{log_debug, log_important};
+use anyhow::Result;
+use tauri::menu::{Menu, MenuItem};
+use tauri::tray::TrayIconBuilder;
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::io::{AsyncRead, AsyncWrite, BufReader};
+
+/// 常駐模式命令列參數
+pub const DAEMON_ARG: &str = "--daemon";
+
+/// 弹窗結束（已回應或被取消）時通知前端的事件
+pub const MCP_REQUEST_CLOSED_EVENT: &str = "mcp-request-closed";
+
+/// 收到進度通知時通知前端的事件
+pub const MCP_NOTIFICATION_EVENT: &str = "mcp-notification";
+
+/// 從系統匣開啟通知紀錄時通知前端的事件
+pub const SHOW_NOTIFICATION_LOG_EVENT: &str = "show-notification-log";
+
+/// 檢查是否以常駐模式執行
+pub fn is_daemon_mode() -> bool {
+    std::env::args().nth(1).as_deref() == Some(DAEMON_ARG)
+}
+
+/// 啟動常駐模式：建立系統匣圖示並開始監聽 IPC 請求
+pub fn start_daemon(app_handle: &AppHandle) -> Result<()> {
+    // 常駐模式啟動時只顯示系統匣，收到請求才顯示視窗
+    if let Some(window) = app_handle.get_webview_window("main") {
+        let _ = window.hide();
+    }
+
+    setup_tray(app_handle)?;
+
+    let app_handle = app_handle.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = listen(app_handle).await {
+            log_important!(error, "常駐程序 IPC 監聽失敗: {}", e);
+        }
+    });
+
+    Ok(())
+}
+
+/// 常駐模式下以隱藏視窗取代退出
+pub fn hide_main_window(app_handle: &AppHandle) {
+    if let Some(window) = app_handle.get_webview_window("main") {
+        let _ = window.hide();
+    }
+}
+
+/// 建立系統匣圖示与選單
+fn setup_tray(app_handle: &AppHandle) -> Result<()> {
+    let show_item = MenuItem::with_id(app_handle, "show", "開啟設定", true, None::<&str>)?;
+    let notifications_item = MenuItem::with_id(app_handle, "notifications", "通知紀錄", true, None::<&str>)?;
+    let quit_item = MenuItem::with_id(app_handle, "quit", "退出", true, None::<&str>)?;
+    let menu = Menu::with_items(app_handle, &[&show_item, &notifications_item, &quit_item])?;
+
+    let mut builder = TrayIconBuilder::with_id("main")
+        .tooltip("寸止")
+        .menu(&menu)
+        .on_menu_event(|app, event| match event.id.as_ref() {
+            "show" => {
+                if let Some(window) = app.get_webview_window("main") {
+                    let _ = window.show();
+                    let _ = window.set_focus();
+                }
+            }
+            "notifications" => {
+                if let Some(window) = app.get_webview_window("main") {
+                    let _ = window.show();
+                    let _ = window.set_focus();
+                }
+                let _ = app.emit(SHOW_NOTIFICATION_LOG_EVENT, ());
+            }
+            "quit" => app.exit(0),
+            _ => {}
+        });
+    if let Some(icon) = app_handle.default_window_icon() {
+        builder = builder.icon(icon.clone());
+    }
+    builder.build(app_handle)?;
+
+    Ok(())
+}
+
+/// 監聽 Unix domain socket
+#[cfg(unix)]
+async fn listen(app_handle: AppHandle) -> Result<()> {
+    let endpoint = daemon_endpoint()?;
+
+    // 已有常駐程序在監聽時不重複啟動；殘留的 socket 檔案則移除
+    if endpoint.exists() {
+        if tokio::net::UnixStream::connect(&endpoint).await.is_ok() {
+            anyhow::bail!("已有常駐的等一下正在執行: {:?}", endpoint);
+        }
+        std::fs::remove_file(&endpoint)?;
+    }
+
+    // 建立 socket 前先收緊 umask，socket 從建立起就只有目前使用者能存取
+    // SAFETY: umask 沒有前置條件；建立後立即還原
+    let previous_umask = unsafe { libc::umask(0o177) };
+    let listener = tokio::net::UnixListener::bind(&endpoint);
+    unsafe { libc::umask(previous_umask) };
+    let listener = listener?;
+    log_important!(info, "常駐等一下已啟動，監聽: {:?}", endpoint);
+
+    loop {
+        let (stream, _) = listener.accept().await?;
+        if let Err(e) = verify_peer(&stream) {
+            log_important!(warn, "拒絕 IPC 連線: {}", e);
+            continue;
+        }
+        let app_handle = app_handle.clone();
+        tauri::async_runtime::spawn(async move {
+            if let Err(e) = handle_connection(app_handle, stream).await {
+                log_important!(warn, "處理 IPC 連線失敗: {}", e);
+            }
+        });
+    }
+}
+
+/// 監聽 named pipe
+#[cfg(windows)]
+async fn listen(app_handle: AppHandle) -> Result<()> {
+    use tokio::net::windows::named_pipe::ServerOptions;
+
+    let endpoint = daemon_endpoint();
+    let mut server = ServerOptions::new()
+        .first_pipe_instance(true)
+        .create(&endpoint)
+        .map_err(|e| anyhow::anyhow!("已有常駐的等一下正在執行或無法建立 {}: {}", endpoint, e))?;
+    log_important!(info, "常駐等一下已啟動，監聽: {}", endpoint);
+
+    loop {
+        server.connect().await?;
+        // 先建立下一個實例再處理目前連線，避免客戶端在空檔連線失敗
+        let stream = std::mem::replace(&mut server, ServerOptions::new().create(&endpoint)?);
+        let app_handle = app_handle.clone();
+        tauri::async_runtime::spawn(async move {
+            if let Err(e) = handle_connection(app_handle, stream).await {
+                log_important!(warn, "處理 IPC 連線失敗: {}", e);
+            }
+        });
+    }
+}
+
+/// 處理單一連線：顯示弹窗並等待回應或客戶端斷線，或顯示進度通知
+async fn handle_connection<S>(app_handle: AppHandle, stream: S) -> Result<()>
+where
+    S: AsyncRead + AsyncWrite + Send + 'static,
+{
+    let (reader, mut writer) = tokio::io::split(stream);
+    let mut reader = BufReader::new(reader);
+
+    let Some(message) = read_message::<DaemonMessage>(&mut reader).await? else {
+        return Ok(());
+    };
+
+    match message {
+        DaemonMessage::Popup { request } => {
+            let request_id = request.id.clone();
+            let receiver = show_popup(&app_handle, &request)?;
+
+            // 讀取端收到 EOF 代表寸止已取消請求或斷線
+            let disconnected = async {
+                let _ = read_message::<serde_json::Value>(&mut reader).await;
+            };
+
+            tokio::select! {
+                response = receiver => {
+                    let response = response.unwrap_or_else(|_| "用户取消了操作".to_string());
+                    log_debug!("常駐弹窗 {} 已回應", request_id);
+                    write_message(&mut writer, &DaemonReply { response }).await?;
+                }
+                _ = disconnected => {
+                    log_important!(info, "請求 {} 已由寸止取消，關閉弹窗", request_id);
+                }
+            }
+
+            close_popup(&app_handle, &request_id);
+        }
+        DaemonMessage::Notify { notification } => show_notification(&app_handle, &notification)?,
+    }
+
+    Ok(())
+}
+
+/// 登記回應通道、顯示弹窗並傳回等待回應的通道
+///
+/// 多個請求同時存在時前端以分頁排隊顯示，回應依 `request_id` 傳回對應的連線
+fn show_popup(
+    app_handle: &AppHandle,
+    request: &PopupRequest,
+) -> Result<tokio::sync::oneshot::Receiver<String>> {
+    let (sender, receiver) = tokio::sync::oneshot::channel();
+    {
+        let state = app_handle.state::<AppState>();
+        let mut channels = state
+            .response_channels
+            .lock()
+            .map_err(|e| anyhow::anyhow!("獲取回應通道失敗: {}", e))?;
+        channels.insert(request.id.clone(), sender);
+    }
+    update_tray(app_handle);
+
+    if let Some(window) = app_handle.get_webview_window("main") {
+        let _ = window.show();
+        let _ = window.unminimize();
+        let _ = window.set_focus();
+    }
+    app_handle.emit("mcp-request", request)?;
+
+    Ok(receiver)
+}
+
+/// 在系統匣顯示通知摘要、通知前端顯示提示，並依需要播放提示音
+///
+/// 不會顯示或聚焦視窗，避免打斷使用者；視窗已開啟時前端以非模態提示顯示
+fn show_notification(app_handle: &AppHandle, notification: &Notification) -> Result<()> {
+    log_debug!("收到進度通知: {}", notification.summary());
+    {
+        let state = app_handle.state::<AppState>();
+        if let Ok(mut latest) = state.latest_notification.lock() {
+            *latest = Some(notification.summary());
+        };
+    }
+    update_tray(app_handle);
+    app_handle.emit(MCP_NOTIFICATION_EVENT, notification)?;
+
+    if notification.play_sound {
+        let audio = {
+            let state = app_handle.state::<AppState>();
+            let config = state.config.lock().map_err(|e| anyhow::anyhow!("獲取設定失敗: {}", e))?;
+            config
+                .audio_config
+                .notification_enabled
+                .then(|| config.audio_config.custom_url.clone())
+        };
+        if let Some(audio_url) = audio {
+            let app_handle = app_handle.clone();
+            tauri::async_runtime::spawn(async move {
+                if let Err(e) = play_audio_file(&app_handle, &audio_url).await {
+                    log_important!(warn, "播放通知音訊失敗: {}", e);
+                }
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// 移除請求的回應通道，通知前端關閉對應分頁，沒有其他待回應請求時隱藏視窗
+fn close_popup(app_handle: &AppHandle, request_id: &str) {
+    {
+        let state = app_handle.state::<AppState>();
+        if let Ok(mut channels) = state.response_channels.lock() {
+            channels.remove(request_id);
+        };
+    }
+    update_tray(app_handle);
+
+    let _ = app_handle.emit(MCP_REQUEST_CLOSED_EVENT, request_id);
+    hide_if_idle(app_handle);
+}
+
+/// 獲取待回應的請求數量
+fn pending_count(app_handle: &AppHandle) -> usize {
+    let state = app_handle.state::<AppState>();
+    let count = state.response_channels.lock().map(|channels| channels.len()).unwrap_or(0);
+    count
+}
+
+/// 沒有待回應的請求時隱藏視窗
+pub fn hide_if_idle(app_handle: &AppHandle) {
+    if pending_count(app_handle) == 0 {
+        hide_main_window(app_handle);
+    }
+}
+
+/// 在系統匣顯示待回應的請求數量与最近一則進度通知
+fn update_tray(app_handle: &AppHandle) {
+    let Some(tray) = app_handle.tray_by_id("main") else {
+        return;
+    };
+
+    let count = pending_count(app_handle);
+    let (mut tooltip, title) = if count == 0 {
+        ("寸止".to_string(), None)
+    } else {
+        (format!("寸止 - {} 個待回應", count), Some(count.to_string()))
+    };
+    let latest = {
+        let state = app_handle.state::<AppState>();
+        let latest = state.latest_notification.lock().ok().and_then(|latest| latest.clone());
+        latest
+    };
+    if let Some(latest) = latest {
+        tooltip.push('\n');
+        tooltip.push_str(&latest);
+    }
+    let _ = tray.set_tooltip(Some(tooltip));
+    // 標題只在 macOS 選單列顯示，其他平台會忽略
+    let _ = tray.set_title(title);
+}
@@ -2,8 +2,10 @@ pub mod setup;
 pub mod commands;
 pub mod builder;
 pub mod cli;
+pub mod daemon;
 
 pub use setup::*;
 pub use commands::*;
 pub use builder::*;
 pub use cli::*;
+pub use daemon::*;
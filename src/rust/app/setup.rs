@@ -1,3 +1,4 @@
+use crate::app::daemon::{is_daemon_mode, start_daemon};
 use crate::config::{AppState, load_config_and_apply_window_settings};
 use crate::ui::{initialize_audio_asset_manager, setup_window_event_listeners};
 use crate::ui::exit_handler::setup_exit_handlers;
@@ -21,6 +22,13 @@ pub async fn setup_application(app_handle: &AppHandle) -> Result<(), String> {
     // 設定視窗事件監聽器
     setup_window_event_listeners(app_handle);
 
+    // 常駐模式：建立系統匣並監聽寸止的弹窗請求
+    if is_daemon_mode() {
+        if let Err(e) = start_daemon(app_handle) {
+            log_important!(error, "啟動常駐模式失敗: {}", e);
+        }
+    }
+
     // 設定退出處理器
     if let Err(e) = setup_exit_handlers(app_handle) {
         log_important!(warn, "設定退出處理器失敗: {}", e);
@@ -4,6 +4,19 @@ use cunzhi::mcp::handlers::cleanup_old_temp_files;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // 處理幫助和版本參數
+    match std::env::args().nth(1).as_deref() {
+        Some("--help") | Some("-h") => {
+            print_help();
+            return Ok(());
+        }
+        Some("--version") | Some("-v") => {
+            println!("寸止 v{}", env!("CARGO_PKG_VERSION"));
+            return Ok(());
+        }
+        _ => {}
+    }
+
     // 自動初始化日誌系統
     auto_init_logger()?;
 
@@ -14,3 +27,18 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     run_server().await
 }
+
+/// 顯示幫助訊息
+fn print_help() {
+    println!("寸止 - MCP 伺服器");
+    println!();
+    println!("用法:");
+    println!("  寸止                                      以 stdio 傳輸啟動（預設）");
+    println!("  寸止 --transport http [--listen <位址>]    以 Streamable HTTP 傳輸啟動");
+    println!("       [--auth-token <權杖>]               要求 Authorization: Bearer 認證（建議改用環境變數）");
+    println!("  寸止 --help                               顯示此幫助訊息");
+    println!("  寸止 --version                            顯示版本訊息");
+    println!();
+    println!("HTTP 模式預設監聽 127.0.0.1:8848，端點為 /mcp；");
+    println!("認證權杖請透過 CUNZHI_MCP_TOKEN 環境變數設定；--auth-token 的值會出現在程序列表中，其他本機使用者也能看到。");
+}
@@ -110,10 +110,22 @@ pub struct ReplyConfig {
 pub struct McpConfig {
     #[serde(default = "default_mcp_tools")]
     pub tools: HashMap<String, bool>, // MCP工具啟用狀態
+    #[serde(default = "default_progress_interval_secs")]
+    pub progress_interval_secs: u64, // 等待回應時的進度通知間隔（秒），0 表示停用
+    #[serde(default = "default_zhi_timeout_secs")]
+    pub zhi_timeout_secs: u64, // 寸止預設逾時（秒），0 表示不逾時
+    #[serde(default = "default_interaction_mode")]
+    pub interaction_mode: String, // "popup", "elicitation", "elicitation_fallback", "terminal"
+    #[serde(default = "default_terminal_fallback")]
+    pub terminal_fallback: bool, // 沒有圖形介面或弹窗失敗時改用終端
+    #[serde(default)]
+    pub tmux_pane: String, // 終端詢問使用的 tmux 窗格（例如 "main:1.0"），空白表示 /dev/tty
+    #[serde(default = "default_memory_auto_merge")]
+    pub memory_auto_merge: bool, // 新增的記憶与既有記憶重複時，直接以新內容取代既有記憶
 }
 
 // 自訂prompt結構
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct CustomPrompt {
     pub id: String,
     pub name: String,
@@ -133,7 +145,7 @@ pub struct CustomPrompt {
 }
 
 // 自訂prompt設定
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct CustomPromptConfig {
     #[serde(default = "default_custom_prompts")]
     pub prompts: Vec<CustomPrompt>,
@@ -177,7 +189,10 @@ pub struct ShortcutKey {
 #[derive(Debug)]
 pub struct AppState {
     pub config: Mutex<AppConfig>,
-    pub response_channel: Mutex<Option<tokio::sync::oneshot::Sender<String>>>,
+    // 等待回應的請求（request_id -> 回應通道），常駐模式下可同時有多個
+    pub response_channels: Mutex<HashMap<String, tokio::sync::oneshot::Sender<String>>>,
+    // 常駐模式下最近一則進度通知的摘要，顯示在系統匣提示
+    pub latest_notification: Mutex<Option<String>>,
     // 防誤觸退出機制
     pub exit_attempt_count: Mutex<u32>,
     pub last_exit_attempt: Mutex<Option<std::time::Instant>>,
@@ -200,7 +215,8 @@ impl Default for AppState {
     fn default() -> Self {
         Self {
             config: Mutex::new(AppConfig::default()),
-            response_channel: Mutex::new(None),
+            response_channels: Mutex::new(HashMap::new()),
+            latest_notification: Mutex::new(None),
             exit_attempt_count: Mutex::new(0),
             last_exit_attempt: Mutex::new(None),
         }
@@ -228,6 +244,12 @@ pub fn default_audio_config() -> AudioConfig {
 pub fn default_mcp_config() -> McpConfig {
     McpConfig {
         tools: default_mcp_tools(),
+        progress_interval_secs: default_progress_interval_secs(),
+        zhi_timeout_secs: default_zhi_timeout_secs(),
+        interaction_mode: default_interaction_mode(),
+        terminal_fallback: default_terminal_fallback(),
+        tmux_pane: String::new(),
+        memory_auto_merge: default_memory_auto_merge(),
     }
 }
 
@@ -320,9 +342,32 @@ pub fn default_mcp_tools() -> HashMap<String, bool> {
     let mut tools = HashMap::new();
     tools.insert(mcp::TOOL_ZHI.to_string(), true); // 寸止工具預設啟用
     tools.insert(mcp::TOOL_JI.to_string(), true); // 記憶管理工具預設啟用
+    tools.insert(mcp::TOOL_ZHI_FORM.to_string(), true);
+    tools.insert(mcp::TOOL_ZHI_ASK.to_string(), true);
+    tools.insert(mcp::TOOL_ZHI_NOTIFY.to_string(), true);
     tools
 }
 
+pub fn default_progress_interval_secs() -> u64 {
+    mcp::DEFAULT_PROGRESS_INTERVAL_SECS
+}
+
+pub fn default_zhi_timeout_secs() -> u64 {
+    mcp::DEFAULT_ZHI_TIMEOUT_SECS
+}
+
+pub fn default_interaction_mode() -> String {
+    mcp::INTERACTION_MODE_POPUP.to_string()
+}
+
+pub fn default_terminal_fallback() -> bool {
+    mcp::DEFAULT_TERMINAL_FALLBACK
+}
+
+pub fn default_memory_auto_merge() -> bool {
+    mcp::DEFAULT_MEMORY_AUTO_MERGE
+}
+
 pub fn default_window_width() -> f64 {
     window::DEFAULT_WIDTH
 }
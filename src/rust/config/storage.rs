@@ -140,7 +140,7 @@ pub fn load_standalone_config() -> Result<AppConfig> {
 // Telegram 功能已移除
 
 /// 獲取独立設定檔案路径（不依赖Tauri）
-fn get_standalone_config_path() -> Result<PathBuf> {
+pub fn get_standalone_config_path() -> Result<PathBuf> {
     // 使用標準的設定目录
     let config_dir = dirs::config_dir()
         .ok_or_else(|| anyhow::anyhow!("無法獲取設定目录"))?
@@ -6,8 +6,17 @@ pub const TOOL_ZHI: &str = "zhi";
 /// 記憶管理工具標識符
 pub const TOOL_JI: &str = "ji";
 
+/// 表單工具標識符
+pub const TOOL_ZHI_FORM: &str = "zhi_form";
+
+/// 非阻塞詢問工具標識符，同時控制 `zhi_poll` 与 `zhi_wait`
+pub const TOOL_ZHI_ASK: &str = "zhi_ask";
+
+/// 進度通知工具標識符
+pub const TOOL_ZHI_NOTIFY: &str = "zhi_notify";
+
 /// 預設啟用的工具列表
-pub const DEFAULT_ENABLED_TOOLS: &[&str] = &[TOOL_ZHI, TOOL_JI];
+pub const DEFAULT_ENABLED_TOOLS: &[&str] = &[TOOL_ZHI, TOOL_JI, TOOL_ZHI_FORM, TOOL_ZHI_ASK, TOOL_ZHI_NOTIFY];
 
 /// 繼續回覆預設啟用狀態
 pub const DEFAULT_CONTINUE_REPLY_ENABLED: bool = true;
@@ -24,6 +33,108 @@ pub const REQUEST_TIMEOUT_MS: u64 = 30000;
 /// MCP 重試次數
 pub const MAX_RETRY_COUNT: u32 = 3;
 
+/// 專案記憶目錄名稱
+pub const MEMORY_DIR_NAME: &str = ".cunzhi-memory";
+
+/// 使用者層級全域記憶的目錄名稱（位於設定目錄下）
+pub const GLOBAL_MEMORY_DIR_NAME: &str = "memory";
+
+/// 記憶資源 URI 協定
+pub const MEMORY_RESOURCE_SCHEME: &str = "cunzhi-memory";
+
+/// 記憶檢索預設傳回的數量
+pub const DEFAULT_MEMORY_RECALL_LIMIT: usize = 8;
+
+/// 記憶檢索最多傳回的數量
+pub const MEMORY_RECALL_MAX_LIMIT: usize = 50;
+
+/// 同分類記憶的相似度達到此值時視為重複（0 到 1）
+pub const MEMORY_DUPLICATE_SIMILARITY: f64 = 0.8;
+
+/// 預設不自動合併重複的記憶，改為提示修改既有記憶
+pub const DEFAULT_MEMORY_AUTO_MERGE: bool = false;
+
+/// 檔案變更輪詢間隔 (ms)
+pub const FILE_WATCH_INTERVAL_MS: u64 = 1500;
+
+/// 等待弹窗回應時檢查客戶端連線的間隔 (ms)
+pub const DISCONNECT_CHECK_INTERVAL_MS: u64 = 1000;
+
+/// 向客戶端查詢 MCP roots 的逾時 (ms)，逾時則改用工作目錄
+pub const ROOTS_REQUEST_TIMEOUT_MS: u64 = 2000;
+
+/// 等待使用者回應時的預設進度通知間隔（秒）
+pub const DEFAULT_PROGRESS_INTERVAL_SECS: u64 = 15;
+
+/// 寸止預設逾時（秒），0 表示不逾時
+pub const DEFAULT_ZHI_TIMEOUT_SECS: u64 = 0;
+
+/// 非阻塞詢問票證的目錄名稱（位於設定目錄下）
+pub const TICKET_DIR_NAME: &str = "tickets";
+
+/// 票證保留時間（秒），超過後自動清理
+pub const TICKET_RETENTION_SECS: u64 = 7 * 24 * 60 * 60;
+
+/// 等待票證回覆時回報存活的間隔（秒）
+pub const TICKET_HEARTBEAT_SECS: u64 = 5;
+
+/// 超過此秒數未回報存活的票證視為無人處理，查詢時由目前程序接手
+pub const TICKET_STALE_SECS: u64 = 15;
+
+/// 弹窗中斷（例如等一下重新啟動）後重新顯示前的等待秒數
+pub const TICKET_RETRY_DELAY_SECS: u64 = 3;
+
+/// 弹窗連續失敗的次數上限，超過後票證標記為失敗
+pub const TICKET_MAX_ATTEMPTS: u32 = 5;
+
+/// `zhi_wait` 檢查票證狀態的間隔 (ms)
+pub const TICKET_POLL_INTERVAL_MS: u64 = 1000;
+
+/// `zhi_wait` 預設最多等待的秒數
+pub const DEFAULT_TICKET_WAIT_SECS: u64 = 60;
+
+/// `zhi` 每次請求最多的附件數量
+pub const ATTACHMENT_MAX_COUNT: usize = 10;
+
+/// 單一附件的大小上限 (位元組)
+pub const ATTACHMENT_MAX_BYTES: usize = 5 * 1024 * 1024;
+
+/// 進度通知日誌的目錄名稱（位於設定目錄下）
+pub const NOTIFICATION_DIR_NAME: &str = "notifications";
+
+/// 每個工作階段保留的通知數量
+pub const NOTIFICATION_LOG_LIMIT: usize = 50;
+
+/// 工作階段通知日誌的保留時間 (秒)，超過後不再顯示並清理
+pub const NOTIFICATION_RETENTION_SECS: u64 = 24 * 60 * 60;
+
+/// 傳送通知給常駐等一下的逾時時間 (ms)，避免 `zhi_notify` 被卡住
+pub const NOTIFICATION_DELIVERY_TIMEOUT_MS: u64 = 1000;
+
+/// 互動模式：僅使用等一下弹窗
+pub const INTERACTION_MODE_POPUP: &str = "popup";
+
+/// 互動模式：僅使用客戶端的 MCP elicitation
+pub const INTERACTION_MODE_ELICITATION: &str = "elicitation";
+
+/// 互動模式：客戶端支援時使用 elicitation，否則或失敗時改用弹窗
+pub const INTERACTION_MODE_ELICITATION_FALLBACK: &str = "elicitation_fallback";
+
+/// 互動模式：在終端（/dev/tty 或 tmux 窗格）詢問
+pub const INTERACTION_MODE_TERMINAL: &str = "terminal";
+
+/// 預設在沒有圖形介面時改用終端詢問
+pub const DEFAULT_TERMINAL_FALLBACK: bool = true;
+
+/// HTTP 傳輸預設監聽位址
+pub const DEFAULT_HTTP_LISTEN: &str = "127.0.0.1:8848";
+
+/// HTTP 傳輸的 MCP 端點路徑
+pub const HTTP_ENDPOINT_PATH: &str = "/mcp";
+
+/// HTTP 傳輸認證權杖的環境變數名稱
+pub const HTTP_AUTH_TOKEN_ENV: &str = "CUNZHI_MCP_TOKEN";
+
 // MCP 工具設定結構體
 #[derive(Debug, Clone)]
 pub struct McpToolConfig {
@@ -59,6 +170,9 @@ impl Default for McpConfig {
             tools: vec![
                 McpToolConfig::new(TOOL_ZHI, true, false), // 寸止工具不可禁用
                 McpToolConfig::new(TOOL_JI, true, true),   // 記憶管理工具可禁用
+                McpToolConfig::new(TOOL_ZHI_FORM, true, true),
+                McpToolConfig::new(TOOL_ZHI_ASK, true, true),
+                McpToolConfig::new(TOOL_ZHI_NOTIFY, true, true),
             ],
             continue_reply_enabled: DEFAULT_CONTINUE_REPLY_ENABLED,
             auto_continue_threshold: DEFAULT_AUTO_CONTINUE_THRESHOLD,
@@ -120,5 +234,5 @@ pub fn get_default_mcp_config() -> McpConfig {
 
 /// 檢查是否為有效的工具 ID
 pub fn is_valid_tool_id(tool_id: &str) -> bool {
-    matches!(tool_id, TOOL_ZHI | TOOL_JI)
+    matches!(tool_id, TOOL_ZHI | TOOL_JI | TOOL_ZHI_FORM | TOOL_ZHI_ASK | TOOL_ZHI_NOTIFY)
 }
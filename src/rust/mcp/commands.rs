@@ -4,6 +4,8 @@ use tauri::{AppHandle, State};
 
 use crate::config::{AppState, save_config};
 use crate::constants::mcp;
+use crate::mcp::tools::interaction::notification::NotificationLog;
+use crate::mcp::types::NotificationSession;
 
 /// MCP工具設定
 #[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
@@ -44,6 +46,36 @@ pub async fn get_mcp_tools_config(state: State<'_, AppState>) -> Result<Vec<MCPT
             icon_bg: "bg-green-100 dark:bg-green-900".to_string(),
             dark_icon_bg: "dark:bg-green-800".to_string(),
         },
+        MCPToolConfig {
+            id: mcp::TOOL_ZHI_FORM.to_string(),
+            name: "表單".to_string(),
+            description: "依 JSON Schema 顯示表單，收集經過驗證的結構化值".to_string(),
+            enabled: config.mcp_config.tools.get(mcp::TOOL_ZHI_FORM).copied().unwrap_or(true),
+            can_disable: true,
+            icon: "i-carbon-list-checked".to_string(),
+            icon_bg: "bg-purple-100 dark:bg-purple-900".to_string(),
+            dark_icon_bg: "dark:bg-purple-800".to_string(),
+        },
+        MCPToolConfig {
+            id: mcp::TOOL_ZHI_ASK.to_string(),
+            name: "非阻塞詢問".to_string(),
+            description: "zhi_ask 在背景顯示弹窗並傳回票證，以 zhi_poll、zhi_wait 取得回覆".to_string(),
+            enabled: config.mcp_config.tools.get(mcp::TOOL_ZHI_ASK).copied().unwrap_or(true),
+            can_disable: true,
+            icon: "i-carbon-ticket".to_string(),
+            icon_bg: "bg-orange-100 dark:bg-orange-900".to_string(),
+            dark_icon_bg: "dark:bg-orange-800".to_string(),
+        },
+        MCPToolConfig {
+            id: mcp::TOOL_ZHI_NOTIFY.to_string(),
+            name: "進度通知".to_string(),
+            description: "發出不需要回覆的進度或狀態通知".to_string(),
+            enabled: config.mcp_config.tools.get(mcp::TOOL_ZHI_NOTIFY).copied().unwrap_or(true),
+            can_disable: true,
+            icon: "i-carbon-notification".to_string(),
+            icon_bg: "bg-yellow-100 dark:bg-yellow-900".to_string(),
+            dark_icon_bg: "dark:bg-yellow-800".to_string(),
+        },
     ];
     
     // 按啟用狀態排序，啟用的在前
@@ -112,3 +144,23 @@ pub async fn reset_mcp_tools_config(
     log::info!("MCP工具設定已重置为預設值");
     Ok(())
 }
+
+/// 獲取各工作階段的進度通知日誌，最近更新的在前
+#[tauri::command]
+pub async fn get_notification_log() -> Result<Vec<NotificationSession>, String> {
+    NotificationLog::open()
+        .and_then(|log| log.sessions())
+        .map_err(|e| format!("讀取通知日誌失敗: {}", e))
+}
+
+/// 清除所有進度通知日誌
+#[tauri::command]
+pub async fn clear_notification_log(state: State<'_, AppState>) -> Result<(), String> {
+    NotificationLog::open()
+        .and_then(|log| log.clear())
+        .map_err(|e| format!("清除通知日誌失敗: {}", e))?;
+    if let Ok(mut latest) = state.latest_notification.lock() {
+        *latest = None;
+    }
+    Ok(())
+}
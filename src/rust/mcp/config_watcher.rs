@@ -0,0 +1,168 @@
+//! MCP 伺服器設定快取
+//!
+//! 快取解析後的 `AppConfig`，輪詢設定檔修改時間並自動重新載入；
+//! 工具啟用狀態或自訂提示詞變更時通知已連線的客戶端
+
+use rmcp::{Peer, RoleServer};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, SystemTime};
+
+use super::utils::file_modified_time;
+use crate::config::{get_standalone_config_path, load_standalone_config, AppConfig};
+use crate::constants::mcp::FILE_WATCH_INTERVAL_MS;
+use crate::{log_debug, log_important};
+
+/// 設定快取（stdio 模式每個行程一份，HTTP 模式由所有工作階段共用）
+pub struct ConfigCache {
+    config: RwLock<Arc<AppConfig>>,
+    /// 上次載入時設定檔的修改時間
+    modified: Mutex<Option<SystemTime>>,
+    /// 需要接收變更通知的客戶端
+    peers: Mutex<Vec<Peer<RoleServer>>>,
+    watcher_started: AtomicBool,
+}
+
+impl Default for ConfigCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ConfigCache {
+    /// 載入設定檔建立快取，失敗時使用預設設定
+    pub fn new() -> Self {
+        let config = load_standalone_config().unwrap_or_else(|e| {
+            log_important!(warn, "無法載入設定檔案，使用預設設定: {}", e);
+            AppConfig::default()
+        });
+
+        Self {
+            config: RwLock::new(Arc::new(config)),
+            modified: Mutex::new(config_modified_time()),
+            peers: Mutex::new(Vec::new()),
+            watcher_started: AtomicBool::new(false),
+        }
+    }
+
+    /// 取得目前快取的設定
+    pub fn current(&self) -> Arc<AppConfig> {
+        match self.config.read() {
+            Ok(guard) => Arc::clone(&guard),
+            Err(poisoned) => Arc::clone(&poisoned.into_inner()),
+        }
+    }
+
+    /// 檢查工具是否啟用（未設定的工具視為啟用）
+    pub fn is_tool_enabled(&self, tool_name: &str) -> bool {
+        let enabled = self.current().mcp_config.tools.get(tool_name).copied().unwrap_or(true);
+        log_debug!("工具 {} 當前狀態: {}", tool_name, enabled);
+        enabled
+    }
+
+    /// 綁定客戶端並啟動設定檔監看
+    pub fn attach_peer(self: &Arc<Self>, peer: Peer<RoleServer>) {
+        if let Ok(mut peers) = self.peers.lock() {
+            peers.push(peer);
+        }
+        self.ensure_watcher();
+    }
+
+    /// 若設定檔已修改則重新載入，傳回工具列表與提示詞是否有變更
+    fn reload_if_changed(&self) -> Option<ConfigChanges> {
+        let modified = config_modified_time();
+        {
+            let last_modified = self.modified.lock().ok()?;
+            if *last_modified == modified {
+                return None;
+            }
+        }
+
+        // 解析失敗（例如設定介面正在寫入）時保留舊設定，下次輪詢再試
+        let config = match load_standalone_config() {
+            Ok(config) => config,
+            Err(e) => {
+                log_debug!("重新載入設定失敗，沿用快取: {}", e);
+                return None;
+            }
+        };
+
+        if let Ok(mut last_modified) = self.modified.lock() {
+            *last_modified = modified;
+        }
+
+        let previous = self.current();
+        let changes = ConfigChanges {
+            tools: previous.mcp_config.tools != config.mcp_config.tools,
+            prompts: previous.custom_prompt_config != config.custom_prompt_config,
+        };
+
+        log_important!(info, "設定檔已變更，重新載入設定");
+        if let Ok(mut guard) = self.config.write() {
+            *guard = Arc::new(config);
+        }
+
+        Some(changes)
+    }
+
+    /// 啟動輪詢任務，所有客戶端斷線後結束
+    fn ensure_watcher(self: &Arc<Self>) {
+        if self.watcher_started.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        let cache = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_millis(FILE_WATCH_INTERVAL_MS));
+            loop {
+                interval.tick().await;
+
+                let Some(peers) = cache.live_peers_or_stop() else {
+                    break;
+                };
+
+                let Some(changes) = cache.reload_if_changed() else {
+                    continue;
+                };
+
+                for peer in peers {
+                    if changes.tools {
+                        if let Err(e) = peer.notify_tool_list_changed().await {
+                            log_important!(warn, "傳送工具列表變更通知失敗: {}", e);
+                        }
+                    }
+                    if changes.prompts {
+                        if let Err(e) = peer.notify_prompt_list_changed().await {
+                            log_important!(warn, "傳送提示詞列表變更通知失敗: {}", e);
+                        }
+                    }
+                }
+            }
+        });
+    }
+
+    /// 移除已斷線的客戶端並傳回其餘客戶端；已沒有客戶端時標記監看結束並傳回 None
+    ///
+    /// 標記在持有 `peers` 鎖時清除，之後綁定的客戶端一定會重新啟動監看
+    fn live_peers_or_stop(&self) -> Option<Vec<Peer<RoleServer>>> {
+        let mut peers = self.peers.lock().unwrap_or_else(|e| e.into_inner());
+        peers.retain(|peer| !peer.is_transport_closed());
+        if peers.is_empty() {
+            self.watcher_started.store(false, Ordering::SeqCst);
+            return None;
+        }
+        Some(peers.clone())
+    }
+}
+
+/// 重新載入後的變更內容
+struct ConfigChanges {
+    tools: bool,
+    prompts: bool,
+}
+
+fn config_modified_time() -> Option<SystemTime> {
+    get_standalone_config_path()
+        .ok()
+        .and_then(|path| file_modified_time(&path))
+}
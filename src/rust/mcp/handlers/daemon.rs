@@ -0,0 +1,74 @@
+use anyhow::Result;
+use std::time::Duration;
+use tokio::io::BufReader;
+use tokio_util::sync::CancellationToken;
+
+use super::popup::{wait_for_timeout, PopupOutcome};
+use crate::constants::mcp::NOTIFICATION_DELIVERY_TIMEOUT_MS;
+use crate::mcp::types::{Notification, PopupRequest};
+use crate::utils::ipc::{connect_daemon, read_message, write_message, DaemonMessage, DaemonReply};
+use crate::{log_debug, log_important};
+
+/// 透過常駐的等一下顯示弹窗
+///
+/// 常駐程序未執行時傳回 `None`，由呼叫端改為啟動新的 UI 程序。
+/// 取消或逾時時直接關閉連線，常駐程序會據此關閉對應的弹窗
+pub async fn send_to_daemon(
+    request: &PopupRequest,
+    cancel: &CancellationToken,
+) -> Option<Result<PopupOutcome>> {
+    let stream = match connect_daemon().await {
+        Ok(stream) => stream,
+        Err(e) => {
+            log_debug!("常駐等一下未執行，改為啟動新程序: {}", e);
+            return None;
+        }
+    };
+    log_important!(info, "透過常駐等一下顯示弹窗: {}", request.id);
+
+    let (reader, mut writer) = tokio::io::split(stream);
+    let mut reader = BufReader::new(reader);
+
+    let exchange = async {
+        write_message(&mut writer, &DaemonMessage::Popup { request: request.clone() }).await?;
+        match read_message::<DaemonReply>(&mut reader).await? {
+            Some(reply) => Ok(PopupOutcome::Response(reply.response)),
+            None => anyhow::bail!("常駐等一下在回應前關閉了連線"),
+        }
+    };
+
+    let outcome = tokio::select! {
+        outcome = exchange => outcome,
+        _ = cancel.cancelled() => {
+            log_important!(info, "請求 {} 已取消，通知常駐等一下關閉弹窗", request.id);
+            Ok(PopupOutcome::Cancelled)
+        }
+        _ = wait_for_timeout(request.timeout_seconds) => {
+            log_important!(info, "請求 {} 等待逾時，通知常駐等一下關閉弹窗", request.id);
+            Ok(PopupOutcome::TimedOut)
+        }
+    };
+    Some(outcome)
+}
+
+/// 把進度通知交給常駐的等一下顯示，不等待回覆
+///
+/// 傳回是否已送達；常駐程序未執行或逾時時傳回 false
+pub async fn notify_daemon(notification: &Notification) -> bool {
+    let deliver = async {
+        let mut stream = connect_daemon().await?;
+        write_message(&mut stream, &DaemonMessage::Notify { notification: notification.clone() }).await
+    };
+
+    match tokio::time::timeout(Duration::from_millis(NOTIFICATION_DELIVERY_TIMEOUT_MS), deliver).await {
+        Ok(Ok(())) => true,
+        Ok(Err(e)) => {
+            log_debug!("常駐等一下未執行，通知只記錄於日誌: {}", e);
+            false
+        }
+        Err(_) => {
+            log_important!(warn, "傳送通知給常駐等一下逾時: {}", notification.id);
+            false
+        }
+    }
+}
@@ -0,0 +1,529 @@
+use anyhow::Result;
+use rmcp::{model::*, service::ServiceError, Peer, RoleServer};
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+use super::popup::PopupOutcome;
+use crate::mcp::types::{
+    build_form_response, FileReview, McpResponse, PopupRequest, PredefinedOption, QuestionAnswer,
+    ReviewVerdict, SelectionMode,
+};
+use crate::mcp::utils::{display_value, AttachmentKind, FormField, FormFieldKind};
+use crate::{log_debug, log_important};
+
+/// 自由輸入欄位名稱
+const USER_INPUT_FIELD: &str = "user_input";
+
+/// 單選模式的選項欄位名稱
+const SELECTED_OPTION_FIELD: &str = "selected_option";
+
+/// 透過 MCP elicitation 在客戶端介面詢問使用者
+///
+/// 多選時預定義選項以布林欄位呈現（elicitation 不支援陣列），單選時以字串列舉呈現；
+/// 回覆轉換為与弹窗相同的 JSON 格式，
+/// 因此結果可直接交給 `build_tool_result` 處理
+pub async fn create_elicitation(
+    peer: &Peer<RoleServer>,
+    request: &PopupRequest,
+    cancel: &CancellationToken,
+) -> Result<PopupOutcome> {
+    let options = request.predefined_options.clone().unwrap_or_default();
+    let timeout = request.timeout_seconds.map(Duration::from_secs);
+
+    let elicitation = peer.create_elicitation_with_timeout(
+        CreateElicitationRequestParam {
+            message: elicitation_message(request),
+            requested_schema: if request.form_fields.is_empty() {
+                requested_schema(request, &options)
+            } else {
+                form_schema(&request.form_fields)
+            },
+        },
+        timeout,
+    );
+
+    let result = tokio::select! {
+        result = elicitation => result,
+        _ = cancel.cancelled() => {
+            log_important!(info, "請求 {} 已取消，停止等待 elicitation", request.id);
+            return Ok(PopupOutcome::Cancelled);
+        }
+    };
+
+    let result = match result {
+        Ok(result) => result,
+        Err(ServiceError::Timeout { .. }) => {
+            log_important!(info, "請求 {} 的 elicitation 等待逾時", request.id);
+            return Ok(PopupOutcome::TimedOut);
+        }
+        Err(e) => anyhow::bail!("elicitation 請求失敗: {}", e),
+    };
+
+    log_debug!("收到 elicitation 回應: {:?}", result.action);
+    match result.action {
+        ElicitationAction::Accept => {
+            let content = result.content.unwrap_or_default();
+            if request.form_fields.is_empty() {
+                Ok(PopupOutcome::Response(to_send_response(request, &options, &content)))
+            } else {
+                Ok(PopupOutcome::Response(to_form_response(request, &content)))
+            }
+        }
+        // 使用者拒絕或取消都視為取消操作，与關閉弹窗的行為一致
+        ElicitationAction::Decline | ElicitationAction::Cancel => {
+            Ok(PopupOutcome::Response("用户取消了操作".to_string()))
+        }
+    }
+}
+
+/// 組合表單訊息，附上附件內容或說明，有差異時附上 diff 內容
+fn elicitation_message(request: &PopupRequest) -> String {
+    if request.diff_files.is_empty() && request.attachments.is_empty() {
+        return request.message.clone();
+    }
+
+    let mut message = request.message.trim_end().to_string();
+    for attachment in &request.attachments {
+        message.push_str(&format!("\n\n附件：{}", attachment.summary()));
+        if attachment.kind == AttachmentKind::Text {
+            message.push_str(&format!("\n```\n{}\n```", attachment.data.trim_end()));
+        }
+    }
+    if request.diff_files.is_empty() {
+        return message;
+    }
+
+    message.push_str("\n\n```diff");
+    for file in &request.diff_files {
+        message.push_str(&format!("\n--- {}\n+++ {}", file.old_path.as_deref().unwrap_or(&file.path), file.path));
+        for hunk in &file.hunks {
+            message.push('\n');
+            message.push_str(&hunk.header);
+            for line in &hunk.lines {
+                message.push('\n');
+                message.push_str(line);
+            }
+        }
+    }
+    message.push_str("\n```");
+    message
+}
+
+/// 建立 elicitation 的 requestedSchema（僅含基本型別的扁平物件）
+///
+/// 差異區塊的欄位為 `hunk_檔案_區塊`，每個問題的欄位以 `q1_`、`q2_`… 為前綴，整體的選項与回覆不加前綴
+fn requested_schema(request: &PopupRequest, options: &[PredefinedOption]) -> JsonObject {
+    let mut properties = serde_json::Map::new();
+    for (file_index, file) in request.diff_files.iter().enumerate() {
+        for (hunk_index, hunk) in file.hunks.iter().enumerate() {
+            let field = hunk_field(file_index, hunk_index);
+            properties.insert(
+                field.clone(),
+                serde_json::json!({
+                    "type": "string",
+                    "title": format!("{} {}", file.path, hunk.header),
+                    "enum": ["approved", "rejected"],
+                    "enumNames": ["核准", "拒絕"],
+                }),
+            );
+            properties.insert(
+                format!("{}_comment", field),
+                serde_json::json!({
+                    "type": "string",
+                    "title": format!("{} 區塊 {} 的意見", file.path, hunk_index + 1),
+                }),
+            );
+        }
+    }
+
+    for (index, question) in request.questions.iter().enumerate() {
+        let prefix = question_prefix(index);
+        let title = format!("問題 {}：{}", index + 1, question.message.trim());
+        add_option_fields(&mut properties, &prefix, &title, &question.predefined_options, question.selection_mode);
+        properties.insert(
+            format!("{}{}", prefix, USER_INPUT_FIELD),
+            serde_json::json!({
+                "type": "string",
+                "title": format!("問題 {} 的回覆", index + 1),
+                "description": if question.predefined_options.is_empty() { title.clone() } else { "可選，補充說明".to_string() },
+            }),
+        );
+    }
+
+    add_option_fields(&mut properties, "", "選項", options, request.selection_mode);
+    let description = if !request.questions.is_empty() {
+        "可選，整體補充說明"
+    } else if options.is_empty() {
+        "請輸入回覆"
+    } else {
+        "可選，補充說明或其他回覆"
+    };
+    properties.insert(
+        USER_INPUT_FIELD.to_string(),
+        serde_json::json!({
+            "type": "string",
+            "title": "回覆內容",
+            "description": description,
+        }),
+    );
+
+    let mut schema = JsonObject::new();
+    schema.insert("type".to_string(), serde_json::json!("object"));
+    schema.insert("properties".to_string(), serde_json::Value::Object(properties));
+    schema
+}
+
+/// 新增一組選項欄位：單選為字串列舉，多選為每個選項一個布林欄位
+fn add_option_fields(
+    properties: &mut serde_json::Map<String, serde_json::Value>,
+    prefix: &str,
+    title: &str,
+    options: &[PredefinedOption],
+    mode: SelectionMode,
+) {
+    if options.is_empty() {
+        return;
+    }
+
+    if mode == SelectionMode::Single {
+        let mut property = serde_json::json!({
+            "type": "string",
+            "title": title,
+            "enum": options.iter().map(|option| option.id.clone()).collect::<Vec<_>>(),
+            "enumNames": options.iter().map(option_title).collect::<Vec<_>>(),
+        });
+        if let Some(recommended) = options.iter().find(|option| option.recommended) {
+            property["default"] = serde_json::json!(recommended.id);
+        }
+        properties.insert(format!("{}{}", prefix, SELECTED_OPTION_FIELD), property);
+        return;
+    }
+
+    for (index, option) in options.iter().enumerate() {
+        let option_title = if prefix.is_empty() {
+            option_title(option)
+        } else {
+            format!("{} - {}", title, option_title(option))
+        };
+        let mut property = serde_json::json!({
+            "type": "boolean",
+            "title": option_title,
+            "default": false,
+        });
+        if let Some(description) = &option.description {
+            property["description"] = serde_json::json!(description);
+        }
+        properties.insert(format!("{}{}", prefix, option_field(index)), property);
+    }
+}
+
+/// 建立 `zhi_form` 的 requestedSchema
+///
+/// 非字串的列舉值以字串列舉呈現；陣列欄位有可選值時每個值一個布林欄位，否則為以逗號分隔的字串。
+/// 送出的值仍由 MCP 服务器依原始 Schema 驗證
+fn form_schema(fields: &[FormField]) -> JsonObject {
+    let mut properties = serde_json::Map::new();
+    let mut required = Vec::new();
+
+    for field in fields {
+        if field.kind == FormFieldKind::Array && !field.options.is_empty() {
+            for (index, option) in field.options.iter().enumerate() {
+                let default = field
+                    .default
+                    .as_ref()
+                    .and_then(|default| default.as_array())
+                    .is_some_and(|values| values.contains(&option.value));
+                properties.insert(
+                    form_option_field(field, index),
+                    serde_json::json!({
+                        "type": "boolean",
+                        "title": format!("{} - {}", field.title, option.label),
+                        "default": default,
+                    }),
+                );
+            }
+            continue;
+        }
+
+        let mut property = serde_json::Map::new();
+        let kind = match field.kind {
+            // 列舉值一律以字串呈現
+            _ if !field.options.is_empty() => "string",
+            FormFieldKind::Array | FormFieldKind::String => "string",
+            FormFieldKind::Number => "number",
+            FormFieldKind::Integer => "integer",
+            FormFieldKind::Boolean => "boolean",
+        };
+        property.insert("type".to_string(), serde_json::json!(kind));
+        property.insert("title".to_string(), serde_json::json!(field.title));
+        let description = match (field.kind, &field.description) {
+            (FormFieldKind::Array, Some(description)) => Some(format!("{}（多個值以逗號分隔）", description)),
+            (FormFieldKind::Array, None) => Some("多個值以逗號分隔".to_string()),
+            (_, description) => description.clone(),
+        };
+        if let Some(description) = description {
+            property.insert("description".to_string(), serde_json::json!(description));
+        }
+
+        if !field.options.is_empty() {
+            property.insert(
+                "enum".to_string(),
+                serde_json::json!(field.options.iter().map(|option| display_value(&option.value)).collect::<Vec<_>>()),
+            );
+            property.insert(
+                "enumNames".to_string(),
+                serde_json::json!(field.options.iter().map(|option| option.label.clone()).collect::<Vec<_>>()),
+            );
+        }
+        if let Some(default) = &field.default {
+            let default = match (kind, default) {
+                ("string", serde_json::Value::Array(items)) => {
+                    serde_json::json!(items.iter().map(display_value).collect::<Vec<_>>().join(", "))
+                }
+                ("string", value) => serde_json::json!(display_value(value)),
+                (_, value) => value.clone(),
+            };
+            property.insert("default".to_string(), default);
+        }
+        for (key, value) in [("minimum", field.minimum), ("maximum", field.maximum)] {
+            if let Some(value) = value {
+                property.insert(key.to_string(), serde_json::json!(value));
+            }
+        }
+        if field.kind == FormFieldKind::String {
+            for (key, value) in [("minLength", field.min_length), ("maxLength", field.max_length)] {
+                if let Some(value) = value {
+                    property.insert(key.to_string(), serde_json::json!(value));
+                }
+            }
+        }
+
+        if field.required && field.default.is_none() {
+            required.push(field.name.clone());
+        }
+        properties.insert(field.name.clone(), serde_json::Value::Object(property));
+    }
+
+    let mut schema = JsonObject::new();
+    schema.insert("type".to_string(), serde_json::json!("object"));
+    schema.insert("properties".to_string(), serde_json::Value::Object(properties));
+    if !required.is_empty() {
+        schema.insert("required".to_string(), serde_json::json!(required));
+    }
+    schema
+}
+
+/// 將 elicitation 回覆還原為表單值，交由 `build_form_result` 驗證
+fn to_form_response(request: &PopupRequest, content: &serde_json::Value) -> String {
+    let mut values = serde_json::Map::new();
+    for field in &request.form_fields {
+        let value = match (field.kind, field.options.is_empty()) {
+            (FormFieldKind::Array, false) => Some(serde_json::Value::Array(
+                field
+                    .options
+                    .iter()
+                    .enumerate()
+                    .filter(|(index, _)| {
+                        content
+                            .get(form_option_field(field, *index))
+                            .and_then(|v| v.as_bool())
+                            .unwrap_or(false)
+                    })
+                    .map(|(_, option)| option.value.clone())
+                    .collect(),
+            )),
+            (_, false) => content.get(&field.name).and_then(|v| v.as_str()).and_then(|text| {
+                field
+                    .options
+                    .iter()
+                    .find(|option| display_value(&option.value) == text)
+                    .map(|option| option.value.clone())
+            }),
+            (FormFieldKind::Array, true) => text_field(content, &field.name).map(|text| {
+                let numeric = matches!(field.item_kind, Some(FormFieldKind::Number | FormFieldKind::Integer));
+                serde_json::Value::Array(
+                    text.split([',', '，'])
+                        .map(str::trim)
+                        .filter(|item| !item.is_empty())
+                        .map(|item| match serde_json::from_str::<serde_json::Number>(item) {
+                            Ok(number) if numeric => serde_json::Value::Number(number),
+                            _ => serde_json::json!(item),
+                        })
+                        .collect(),
+                )
+            }),
+            _ => content.get(&field.name).cloned(),
+        };
+        if let Some(value) = value {
+            values.insert(field.name.clone(), value);
+        }
+    }
+    build_form_response(serde_json::Value::Object(values), Some(request.id.clone()), "elicitation")
+}
+
+/// 將 elicitation 回覆轉換為弹窗的回應格式
+fn to_send_response(request: &PopupRequest, options: &[PredefinedOption], content: &serde_json::Value) -> String {
+    let answers = request
+        .questions
+        .iter()
+        .enumerate()
+        .map(|(index, question)| {
+            let prefix = question_prefix(index);
+            let selected = selected_from(content, &prefix, &question.predefined_options, question.selection_mode);
+            QuestionAnswer::new(&question.id, selected, text_field(content, &format!("{}{}", prefix, USER_INPUT_FIELD)))
+        })
+        .collect();
+
+    let review = request
+        .diff_files
+        .iter()
+        .enumerate()
+        .map(|(file_index, file)| {
+            let verdicts = (0..file.hunks.len())
+                .map(|hunk_index| {
+                    let field = hunk_field(file_index, hunk_index);
+                    let verdict = match content.get(&field).and_then(|v| v.as_str()) {
+                        Some("approved") => ReviewVerdict::Approved,
+                        Some("rejected") => ReviewVerdict::Rejected,
+                        _ => ReviewVerdict::Pending,
+                    };
+                    (verdict, text_field(content, &format!("{}_comment", field)))
+                })
+                .collect();
+            FileReview::new(file, None, verdicts)
+        })
+        .collect();
+
+    McpResponse {
+        user_input: text_field(content, USER_INPUT_FIELD),
+        answers,
+        review,
+        ..McpResponse::new(Some(request.id.clone()), "elicitation")
+    }
+    .with_selected_options(selected_from(content, "", options, request.selection_mode))
+    .to_string()
+}
+
+/// 讀取一組選項欄位的選擇
+fn selected_from(
+    content: &serde_json::Value,
+    prefix: &str,
+    options: &[PredefinedOption],
+    mode: SelectionMode,
+) -> Vec<PredefinedOption> {
+    let mut selected_options = match mode {
+        SelectionMode::Single => content
+            .get(format!("{}{}", prefix, SELECTED_OPTION_FIELD))
+            .and_then(|v| v.as_str())
+            .and_then(|id| options.iter().find(|option| option.id == id))
+            .cloned()
+            .into_iter()
+            .collect::<Vec<_>>(),
+        SelectionMode::Multiple => options
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| {
+                content
+                    .get(format!("{}{}", prefix, option_field(*index)))
+                    .and_then(|v| v.as_bool())
+                    .unwrap_or(false)
+            })
+            .map(|(_, option)| option.clone())
+            .collect::<Vec<_>>(),
+    };
+
+    // 表單無法限制互斥選項，同時勾選時只保留互斥選項
+    if selected_options.len() > 1 {
+        if let Some(exclusive) = selected_options.iter().find(|option| option.exclusive).cloned() {
+            log_debug!("互斥選項 {} 与其他選項同時被選擇，只保留該選項", exclusive.id);
+            selected_options = vec![exclusive];
+        }
+    }
+    selected_options
+}
+
+/// 讀取文字欄位，空白時傳回 None
+fn text_field(content: &serde_json::Value, field: &str) -> Option<String> {
+    content
+        .get(field)
+        .and_then(|v| v.as_str())
+        .map(|text| text.trim().to_string())
+        .filter(|text| !text.is_empty())
+}
+
+fn form_option_field(field: &FormField, index: usize) -> String {
+    format!("{}_option_{}", field.name, index + 1)
+}
+
+fn hunk_field(file_index: usize, hunk_index: usize) -> String {
+    format!("hunk_{}_{}", file_index + 1, hunk_index + 1)
+}
+
+fn question_prefix(index: usize) -> String {
+    format!("q{}_", index + 1)
+}
+
+/// 選項在表單中顯示的標題，建議選項加上標記
+fn option_title(option: &PredefinedOption) -> String {
+    if option.recommended {
+        format!("{}（建議）", option.label)
+    } else {
+        option.label.clone()
+    }
+}
+
+fn option_field(index: usize) -> String {
+    format!("option_{}", index + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn request() -> PopupRequest {
+        serde_json::from_value(json!({
+            "id": "req-1",
+            "message": "請回答以下問題",
+            "predefined_options": null,
+            "is_markdown": true,
+            "questions": [
+                { "id": "lang", "message": "使用哪種語言？", "predefined_options": ["Rust", "Go"], "selection_mode": "single" },
+                { "id": "extras", "message": "需要哪些功能？", "predefined_options": ["日誌", { "label": "都不要", "exclusive": true }] },
+            ],
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_question_fields_map_back_to_answers() {
+        let request = request();
+
+        // 單選為 q1_ 前綴的列舉，多選為每個選項一個布林欄位
+        let schema = requested_schema(&request, &[]);
+        let properties = schema["properties"].as_object().unwrap();
+        assert_eq!(properties["q1_selected_option"]["enum"], json!(["Rust", "Go"]));
+        assert_eq!(properties["q2_option_2"]["type"], json!("boolean"));
+        assert!(properties.contains_key("q1_user_input"));
+        assert!(!properties.contains_key("selected_option"));
+
+        let content = json!({
+            "q1_selected_option": "Go",
+            "q2_option_1": true,
+            "q2_option_2": true,
+            "q2_user_input": "  只在正式環境  ",
+            "user_input": " ",
+        });
+        let response: McpResponse = serde_json::from_str(&to_send_response(&request, &[], &content)).unwrap();
+        assert_eq!(response.answers.len(), 2);
+        assert_eq!(response.answers[0].question_id, "lang");
+        assert_eq!(response.answers[0].selected_option_ids, vec!["Go"]);
+        assert!(response.answers[0].user_input.is_none());
+        // 互斥選項与其他選項同時勾選時只保留互斥選項
+        assert_eq!(response.answers[1].question_id, "extras");
+        assert_eq!(response.answers[1].selected_option_ids, vec!["都不要"]);
+        assert_eq!(response.answers[1].user_input.as_deref(), Some("只在正式環境"));
+        assert!(response.user_input.is_none());
+        assert_eq!(response.metadata.request_id.as_deref(), Some("req-1"));
+    }
+}
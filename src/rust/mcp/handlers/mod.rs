@@ -1,5 +1,11 @@
+pub mod daemon;
+pub mod elicitation;
 pub mod popup;
 pub mod response;
+pub mod terminal;
 
+pub use daemon::*;
+pub use elicitation::*;
 pub use popup::*;
 pub use response::*;
+pub use terminal::*;
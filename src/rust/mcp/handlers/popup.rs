@@ -1,10 +1,12 @@
 use anyhow::Result;
-use std::process::Command;
+use std::process::{Command, Stdio};
 use std::fs;
 use std::path::{Path, PathBuf};
+use tokio_util::sync::CancellationToken;
 
+use super::daemon::send_to_daemon;
 use crate::mcp::types::PopupRequest;
-use crate::log_debug;
+use crate::{log_debug, log_important};
 
 /// RAII 临時檔案自動清理器
 struct TempFile(PathBuf);
@@ -79,10 +81,29 @@ pub fn cleanup_old_temp_files() {
     }
 }
 
+/// 弹窗執行结果
+pub enum PopupOutcome {
+    /// UI 程序正常结束并傳回的回應
+    Response(String),
+    /// 請求在使用者回應前被取消（客戶端取消或斷線），弹窗已關閉
+    Cancelled,
+    /// 超過 `timeout_seconds` 仍未回應，弹窗已關閉
+    TimedOut,
+}
+
 /// 建立 Tauri 弹窗
 ///
-/// 优先呼叫与 MCP 服务器同目录的 UI 命令，找不到時使用全局版本
-pub fn create_tauri_popup(request: &PopupRequest) -> Result<String> {
+/// 常駐的等一下正在執行時透過 IPC 交給它顯示；否則优先呼叫与 MCP 服务器同目录的 UI 命令，找不到時使用全局版本。
+/// UI 程序以异步子程序執行，`cancel` 被觸發時会終止子程序並傳回 `PopupOutcome::Cancelled`，
+/// 超過請求的 `timeout_seconds` 時則傳回 `PopupOutcome::TimedOut`
+pub async fn create_tauri_popup(
+    request: &PopupRequest,
+    cancel: &CancellationToken,
+) -> Result<PopupOutcome> {
+    if let Some(outcome) = send_to_daemon(request, cancel).await {
+        return outcome;
+    }
+
     // 建立临時請求檔案 - 跨平台适配
     let temp_dir = std::env::temp_dir();
     let temp_file_path = temp_dir.join(format!("mcp_request_{}.json", request.id));
@@ -95,11 +116,27 @@ pub fn create_tauri_popup(request: &PopupRequest) -> Result<String> {
     // 嘗試找到等一下命令的路径
     let command_path = find_ui_command()?;
 
-    // 呼叫等一下命令
-    let output = Command::new(&command_path)
+    // 呼叫等一下命令，子程序在 future 被丢弃時自動終止
+    let child = tokio::process::Command::new(&command_path)
         .arg("--mcp-request")
         .arg(temp_file.path().to_string_lossy().to_string())
-        .output()?;
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+
+    let output = tokio::select! {
+        output = child.wait_with_output() => output?,
+        _ = cancel.cancelled() => {
+            log_important!(info, "請求 {} 已取消，關閉弹窗", request.id);
+            return Ok(PopupOutcome::Cancelled);
+        }
+        _ = wait_for_timeout(request.timeout_seconds) => {
+            log_important!(info, "請求 {} 等待逾時，關閉弹窗", request.id);
+            return Ok(PopupOutcome::TimedOut);
+        }
+    };
 
     // temp_file 会在这里自動清理
 
@@ -107,9 +144,9 @@ pub fn create_tauri_popup(request: &PopupRequest) -> Result<String> {
         let response = String::from_utf8_lossy(&output.stdout);
         let response = response.trim();
         if response.is_empty() {
-            Ok("用户取消了操作".to_string())
+            Ok(PopupOutcome::Response("用户取消了操作".to_string()))
         } else {
-            Ok(response.to_string())
+            Ok(PopupOutcome::Response(response.to_string()))
         }
     } else {
         let error = String::from_utf8_lossy(&output.stderr);
@@ -117,12 +154,18 @@ pub fn create_tauri_popup(request: &PopupRequest) -> Result<String> {
     }
 }
 
+/// 等待逾時，未設定逾時則永不完成
+pub(super) async fn wait_for_timeout(timeout_seconds: Option<u64>) {
+    match timeout_seconds {
+        Some(secs) => tokio::time::sleep(std::time::Duration::from_secs(secs)).await,
+        None => std::future::pending().await,
+    }
+}
+
 /// 查找等一下 UI 命令的路径
 ///
 /// 按优先级查找：同目录 -> 全局版本 -> 开发環境
 fn find_ui_command() -> Result<String> {
-    use crate::log_important;
-
     // 1. 优先嘗試与當前 MCP 服务器同目录的等一下命令
     if let Ok(current_exe) = std::env::current_exe() {
         log_debug!("當前可執行檔案路径: {:?}", current_exe);
@@ -1,19 +1,178 @@
 use anyhow::Result;
-use rmcp::{ErrorData as McpError, model::Content};
+use rmcp::{ErrorData as McpError, model::{CallToolResult, Content}};
 
-use crate::mcp::types::{McpResponse, McpResponseContent};
+use crate::mcp::types::{
+    FileReview, ImageAttachment, McpResponse, McpResponseContent, PredefinedOption, QuestionAnswer,
+    ReviewVerdict,
+};
+use crate::mcp::utils::{validate_form_values, FormField};
+
+/// 將 UI 回應轉換為工具呼叫結果
+///
+/// 文字與圖片內容供一般客戶端顯示，`structuredContent` 則符合 `McpResponse` 的輸出 Schema
+pub fn build_tool_result(response: &str) -> Result<CallToolResult, McpError> {
+    let content = parse_mcp_response(response)?;
+    let structured = serde_json::to_value(to_structured_response(response))
+        .map_err(|e| McpError::internal_error(format!("序列化结构化回應失敗: {}", e), None))?;
+
+    let mut result = CallToolResult::success(content);
+    result.structured_content = Some(structured);
+    Ok(result)
+}
+
+/// 建構請求被取消時的工具呼叫結果
+///
+/// 用於客戶端送出 `notifications/cancelled` 或連線中斷，弹窗已被關閉的情況
+pub fn build_cancelled_result(request_id: &str, reason: &str) -> Result<CallToolResult, McpError> {
+    let mut response = plain_response(None, vec![], "cancelled");
+    response.metadata.request_id = Some(request_id.to_string());
+    let structured = serde_json::to_value(response)
+        .map_err(|e| McpError::internal_error(format!("序列化结构化回應失敗: {}", e), None))?;
+
+    let mut result = CallToolResult::success(vec![Content::text(format!("請求已取消：{}", reason))]);
+    result.structured_content = Some(structured);
+    Ok(result)
+}
+
+/// 建構逾時時的工具呼叫結果
+///
+/// 有預設選項時選擇該選項，否則回覆繼續提示詞
+pub fn build_timeout_result(
+    request_id: &str,
+    default_option: Option<&PredefinedOption>,
+    continue_prompt: &str,
+) -> Result<CallToolResult, McpError> {
+    let mut response = match default_option {
+        Some(option) => McpResponse {
+            selected_options: vec![option.label.clone()],
+            selected_option_ids: vec![option.id.clone()],
+            ..plain_response(None, vec![], "timeout")
+        },
+        None => plain_response(Some(continue_prompt.to_string()), vec![], "timeout"),
+    };
+    response.metadata.request_id = Some(request_id.to_string());
+    response.metadata.timed_out = true;
+
+    let response_json = serde_json::to_string(&response)
+        .map_err(|e| McpError::internal_error(format!("序列化逾時回應失敗: {}", e), None))?;
+    build_tool_result(&response_json)
+}
+
+/// 建構 `zhi_form` 的工具呼叫結果
+///
+/// 依欄位定義驗證表單值，`structuredContent` 為驗證後的值物件；
+/// 使用者取消、沒有填寫表單或驗證失敗時傳回錯誤結果，讓 AI 不會把它當成有效設定
+pub fn build_form_result(response: &str, fields: &[FormField]) -> Result<CallToolResult, McpError> {
+    let response = to_structured_response(response);
+    if response.metadata.source.as_deref() == Some("cancelled") {
+        return Ok(CallToolResult::error(vec![Content::text("用户取消了表單填寫".to_string())]));
+    }
+
+    match &response.form_values {
+        Some(values) => form_values_result(fields, values, false),
+        None => {
+            let mut message = "使用者沒有填寫表單".to_string();
+            if let Some(user_input) = response.user_input.as_deref().map(str::trim).filter(|text| !text.is_empty()) {
+                message.push_str(&format!("，回覆：\n{}", user_input));
+            }
+            Ok(CallToolResult::error(vec![Content::text(message)]))
+        }
+    }
+}
+
+/// 建構 `zhi_form` 逾時時的工具呼叫結果，所有欄位套用預設值
+pub fn build_form_timeout_result(fields: &[FormField]) -> Result<CallToolResult, McpError> {
+    form_values_result(fields, &serde_json::Value::Null, true)
+}
+
+fn form_values_result(
+    fields: &[FormField],
+    values: &serde_json::Value,
+    timed_out: bool,
+) -> Result<CallToolResult, McpError> {
+    let values = match validate_form_values(fields, values) {
+        Ok(values) => serde_json::Value::Object(values),
+        Err(errors) => {
+            let title = if timed_out {
+                "使用者未在時限內填寫，且以下欄位無法套用預設值："
+            } else {
+                "表單驗證失敗："
+            };
+            let message = format!("{}\n- {}", title, errors.join("\n- "));
+            return Ok(CallToolResult::error(vec![Content::text(message)]));
+        }
+    };
+
+    let json = serde_json::to_string_pretty(&values)
+        .map_err(|e| McpError::internal_error(format!("序列化表單值失敗: {}", e), None))?;
+    let text = if timed_out {
+        format!("⏱ 使用者未在時限內填寫，已套用欄位預設值\n\n{}", json)
+    } else {
+        json
+    };
+
+    let mut result = CallToolResult::success(vec![Content::text(text)]);
+    result.structured_content = Some(values);
+    Ok(result)
+}
+
+/// 將任意格式的 UI 回應統一轉換為 `McpResponse`
+fn to_structured_response(response: &str) -> McpResponse {
+    let trimmed = response.trim();
+    if is_cancelled_response(trimmed) {
+        return plain_response(None, vec![], "cancelled");
+    }
+
+    if let Ok(structured_response) = serde_json::from_str::<McpResponse>(trimmed) {
+        return structured_response;
+    }
+
+    // 旧格式：合并文本并收集图片
+    if let Ok(content_array) = serde_json::from_str::<Vec<McpResponseContent>>(trimmed) {
+        let mut texts = Vec::new();
+        let mut images = Vec::new();
+        for content in content_array {
+            match content.source {
+                Some(source) if content.content_type == "image" && source.source_type == "base64" => {
+                    images.push(ImageAttachment {
+                        data: source.data,
+                        media_type: source.media_type,
+                        filename: None,
+                    });
+                }
+                _ => texts.extend(content.text),
+            }
+        }
+        let user_input = if texts.is_empty() { None } else { Some(texts.join("\n\n")) };
+        return plain_response(user_input, images, "legacy");
+    }
+
+    plain_response(Some(trimmed.to_string()), vec![], "text")
+}
+
+fn plain_response(user_input: Option<String>, images: Vec<ImageAttachment>, source: &str) -> McpResponse {
+    McpResponse {
+        user_input,
+        images,
+        ..McpResponse::new(None, source)
+    }
+}
+
+fn is_cancelled_response(response: &str) -> bool {
+    response == "CANCELLED" || response == "用户取消了操作"
+}
 
 /// 解析 MCP 回應內容
 ///
 /// 支持新的结构化格式和旧格式的相容性，并生成适当的 Content 物件
 pub fn parse_mcp_response(response: &str) -> Result<Vec<Content>, McpError> {
-    if response.trim() == "CANCELLED" || response.trim() == "用户取消了操作" {
+    if is_cancelled_response(response.trim()) {
         return Ok(vec![Content::text("用户取消了操作".to_string())]);
     }
 
     // 首先嘗試解析为新的结构化格式
     if let Ok(structured_response) = serde_json::from_str::<McpResponse>(response) {
-        return parse_structured_response(structured_response);
+        return parse_structured_response(&structured_response);
     }
 
     // 回退到旧格式相容性解析
@@ -117,23 +276,38 @@ pub fn parse_mcp_response(response: &str) -> Result<Vec<Content>, McpError> {
 }
 
 /// 解析新的结构化回應格式
-fn parse_structured_response(response: McpResponse) -> Result<Vec<Content>, McpError> {
+fn parse_structured_response(response: &McpResponse) -> Result<Vec<Content>, McpError> {
     let mut result = Vec::new();
     let mut text_parts = Vec::new();
 
+    if response.metadata.timed_out {
+        text_parts.push("⏱ 使用者未在時限內回應，已自動採用預設回覆".to_string());
+    }
+
     // 1. 處理選擇的選項
     if !response.selected_options.is_empty() {
-        text_parts.push(format!("選擇的選項: {}", response.selected_options.join(", ")));
+        text_parts.push(format!(
+            "選擇的選項: {}",
+            format_selected(&response.selected_options, &response.selected_option_ids)
+        ));
     }
 
     // 2. 處理用户輸入文本
-    if let Some(user_input) = response.user_input {
+    if let Some(user_input) = &response.user_input {
         if !user_input.trim().is_empty() {
             text_parts.push(user_input.trim().to_string());
         }
     }
 
-    // 3. 處理图片附件
+    // 3. 處理各問題的答案与差異審查結果
+    for (index, answer) in response.answers.iter().enumerate() {
+        text_parts.push(format_answer(index, answer));
+    }
+    if !response.review.is_empty() {
+        text_parts.push(format_review(&response.review));
+    }
+
+    // 4. 處理图片附件
     let mut image_info_parts = Vec::new();
     for (index, image) in response.images.iter().enumerate() {
         // 新增图片到结果中（图片在前）
@@ -168,11 +342,11 @@ fn parse_structured_response(response: McpResponse) -> Result<Vec<Content>, McpE
         image_info_parts.push(image_info);
     }
 
-    // 4. 合并所有文本內容
+    // 5. 合并所有文本內容
     let mut all_text_parts = text_parts;
     all_text_parts.extend(image_info_parts);
 
-    // 5. 新增相容性说明
+    // 6. 新增相容性说明
     if !response.images.is_empty() {
         all_text_parts.push(format!(
             "💡 注意：用户提供了 {} 张图片。如果 AI 助手無法顯示图片，图片資料已包含在上述 Base64 訊息中。",
@@ -180,16 +354,79 @@ fn parse_structured_response(response: McpResponse) -> Result<Vec<Content>, McpE
         ));
     }
 
-    // 6. 将文本內容新增到结果中（图片后面）
+    // 7. 将文本內容新增到结果中（图片后面）
     if !all_text_parts.is_empty() {
         let combined_text = all_text_parts.join("\n\n");
         result.push(Content::text(combined_text));
     }
 
-    // 7. 如果没有任何內容，新增預設回應
+    // 8. 如果没有任何內容，新增預設回應
     if result.is_empty() {
         result.push(Content::text("用户未提供任何內容".to_string()));
     }
 
     Ok(result)
 }
+
+/// 格式化單一問題的答案
+fn format_answer(index: usize, answer: &QuestionAnswer) -> String {
+    let mut lines = vec![format!("【問題 {}：{}】", index + 1, answer.question_id)];
+    if !answer.selected_options.is_empty() {
+        lines.push(format!("選擇的選項: {}", format_selected(&answer.selected_options, &answer.selected_option_ids)));
+    }
+    if let Some(user_input) = answer.user_input.as_deref().map(str::trim).filter(|text| !text.is_empty()) {
+        lines.push(user_input.to_string());
+    }
+    if lines.len() == 1 {
+        lines.push("（未回答）".to_string());
+    }
+    lines.join("\n")
+}
+
+/// 列出選擇的選項，選項 ID 与文字不同時一併列出
+fn format_selected(labels: &[String], ids: &[String]) -> String {
+    labels
+        .iter()
+        .enumerate()
+        .map(|(index, label)| match ids.get(index) {
+            Some(id) if id != label => format!("{} [id: {}]", label, id),
+            _ => label.clone(),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// 格式化差異審查結果，先列出統計再逐檔列出各區塊
+fn format_review(review: &[FileReview]) -> String {
+    let hunks = review.iter().flat_map(|file| file.hunks.iter());
+    let count = |verdict: ReviewVerdict| hunks.clone().filter(|hunk| hunk.verdict == verdict).count();
+    let mut lines = vec![format!(
+        "=== 差異審查 ===\n核准 {}、拒絕 {}、意見 {}、未審查 {}",
+        count(ReviewVerdict::Approved),
+        count(ReviewVerdict::Rejected),
+        count(ReviewVerdict::Commented),
+        count(ReviewVerdict::Pending),
+    )];
+
+    for file in review {
+        lines.push(String::new());
+        lines.push(file.path.clone());
+        if let Some(comment) = file.comment.as_deref().map(str::trim).filter(|text| !text.is_empty()) {
+            lines.push(format!("  檔案意見: {}", comment));
+        }
+        for hunk in &file.hunks {
+            let verdict = match hunk.verdict {
+                ReviewVerdict::Approved => "✅ 核准",
+                ReviewVerdict::Rejected => "❌ 拒絕",
+                ReviewVerdict::Commented => "💬 意見",
+                ReviewVerdict::Pending => "⏸ 未審查",
+            };
+            let mut line = format!("  {} 區塊 {} {}", verdict, hunk.index, hunk.header);
+            if let Some(comment) = hunk.comment.as_deref().map(str::trim).filter(|text| !text.is_empty()) {
+                line.push_str(&format!("：{}", comment));
+            }
+            lines.push(line);
+        }
+    }
+    lines.join("\n")
+}
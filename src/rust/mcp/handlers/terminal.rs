@@ -0,0 +1,568 @@
+use anyhow::Result;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::Command;
+use tokio_util::sync::CancellationToken;
+
+use super::popup::{wait_for_timeout, PopupOutcome};
+use crate::mcp::types::{
+    build_form_response, FileReview, McpResponse, PopupRequest, PredefinedOption, QuestionAnswer,
+    ReviewVerdict, SelectionMode,
+};
+use crate::mcp::utils::{display_value, AttachmentKind, validate_field, DiffFile, FormField, FormFieldKind};
+use crate::{log_debug, log_important};
+
+/// 預設使用的終端裝置
+const DEFAULT_TTY: &str = "/dev/tty";
+
+/// 等待終端輸入時檢查是否已取消的間隔 (ms)
+const TTY_POLL_INTERVAL_MS: i32 = 200;
+
+/// 檢查目前環境是否能開啟圖形介面
+///
+/// Linux 等平台需要 X11 或 Wayland 顯示伺服器，SSH 或容器內通常沒有
+pub fn gui_available() -> bool {
+    if cfg!(any(target_os = "macos", target_os = "windows")) {
+        return true;
+    }
+
+    ["DISPLAY", "WAYLAND_DISPLAY"]
+        .iter()
+        .any(|name| std::env::var_os(name).is_some_and(|value| !value.is_empty()))
+}
+
+/// 在終端中詢問使用者
+///
+/// 訊息、編號選項与輸入提示寫入 `/dev/tty`，或指定 tmux 窗格（`tmux_pane` 非空時）的終端。
+/// 回應格式与弹窗相同，可直接交給 `build_tool_result` 處理
+pub async fn create_terminal_prompt(
+    request: &PopupRequest,
+    cancel: &CancellationToken,
+    tmux_pane: &str,
+) -> Result<PopupOutcome> {
+    let tty_path = resolve_tty(tmux_pane)?;
+    log_important!(info, "使用終端詢問使用者: {}", tty_path);
+
+    let tty = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(&tty_path)
+        .map_err(|e| anyhow::anyhow!("無法開啟終端 {}: {}", tty_path, e))?;
+
+    // 終端讀取會阻塞，放到阻塞執行緒；取消、逾時或此函數提前結束時停止讀取，
+    // 執行緒在下次檢查時結束，不會留著吃掉使用者之後在 shell 輸入的內容
+    let stop = cancel.child_token();
+    let _stop_guard = stop.clone().drop_guard();
+    let task_request = request.clone();
+    let reader = CancellableTty { tty: tty.try_clone()?, cancel: stop.clone() };
+    let prompt = tokio::task::spawn_blocking(move || prompt_on_tty(tty, reader, &task_request));
+
+    tokio::select! {
+        result = prompt => result.map_err(|e| anyhow::anyhow!("終端詢問任務失敗: {}", e))?,
+        _ = cancel.cancelled() => {
+            log_important!(info, "請求 {} 已取消，停止等待終端輸入", request.id);
+            Ok(PopupOutcome::Cancelled)
+        }
+        _ = wait_for_timeout(request.timeout_seconds) => {
+            log_important!(info, "請求 {} 等待終端輸入逾時", request.id);
+            Ok(PopupOutcome::TimedOut)
+        }
+    }
+}
+
+/// 可取消的終端讀取：先以 poll 等待輸入，等待期間定期檢查是否已取消
+struct CancellableTty {
+    tty: File,
+    cancel: CancellationToken,
+}
+
+impl Read for CancellableTty {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        #[cfg(unix)]
+        {
+            use std::os::unix::io::AsRawFd;
+
+            let mut pollfd = libc::pollfd {
+                fd: self.tty.as_raw_fd(),
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            loop {
+                if self.cancel.is_cancelled() {
+                    // 不可用 Interrupted，BufRead::read_line 遇到時會自動重試
+                    return Err(std::io::Error::other("終端詢問已取消"));
+                }
+                // SAFETY: pollfd 在呼叫期間有效，數量為 1
+                let ready = unsafe { libc::poll(&mut pollfd, 1, TTY_POLL_INTERVAL_MS) };
+                if ready > 0 {
+                    break;
+                }
+                if ready < 0 {
+                    let error = std::io::Error::last_os_error();
+                    if error.kind() != std::io::ErrorKind::Interrupted {
+                        return Err(error);
+                    }
+                }
+            }
+        }
+
+        if self.cancel.is_cancelled() {
+            return Err(std::io::Error::other("終端詢問已取消"));
+        }
+        self.tty.read(buf)
+    }
+}
+
+/// 取得要使用的終端裝置路徑，指定 tmux 窗格時查詢其 `#{pane_tty}`
+fn resolve_tty(tmux_pane: &str) -> Result<String> {
+    if tmux_pane.trim().is_empty() {
+        return Ok(DEFAULT_TTY.to_string());
+    }
+
+    let output = Command::new("tmux")
+        .args(["display-message", "-p", "-t", tmux_pane.trim(), "#{pane_tty}"])
+        .output()
+        .map_err(|e| anyhow::anyhow!("無法執行 tmux: {}", e))?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "找不到 tmux 窗格 {}: {}",
+            tmux_pane,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    let tty_path = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if tty_path.is_empty() {
+        anyhow::bail!("tmux 窗格 {} 沒有對應的終端", tmux_pane);
+    }
+    log_debug!("tmux 窗格 {} 對應終端: {}", tmux_pane, tty_path);
+    Ok(tty_path)
+}
+
+/// 在終端顯示請求並讀取回覆
+fn prompt_on_tty(mut writer: File, reader: CancellableTty, request: &PopupRequest) -> Result<PopupOutcome> {
+    let mut reader = BufReader::new(reader);
+    let options = request.predefined_options.clone().unwrap_or_default();
+
+    writeln!(writer)?;
+    writeln!(writer, "━━━━━━━━━━ 寸止 ━━━━━━━━━━")?;
+    if let Some(origin) = origin_line(request) {
+        writeln!(writer, "{}", origin)?;
+    }
+    writeln!(writer, "{}", request.message.trim())?;
+    writeln!(writer)?;
+    // 終端無法顯示圖片，只列出說明；文字附件直接顯示內容
+    for attachment in &request.attachments {
+        writeln!(writer, "【附件】{}", attachment.summary())?;
+        if attachment.kind == AttachmentKind::Text {
+            writeln!(writer, "{}", attachment.data.trim_end())?;
+        }
+        writeln!(writer)?;
+    }
+    if let Some(secs) = request.timeout_seconds {
+        let fallback = if !request.form_fields.is_empty() {
+            "欄位預設值".to_string()
+        } else {
+            request
+                .default_option
+                .as_ref()
+                .map(|id| {
+                    let label = options.iter().find(|option| &option.id == id).map_or(id, |option| &option.label);
+                    format!("「{}」", label)
+                })
+                .unwrap_or_else(|| "繼續提示詞".to_string())
+        };
+        writeln!(writer, "（{} 秒後自動回覆{}）", secs, fallback)?;
+    }
+
+    if !request.form_fields.is_empty() {
+        return fill_form(&mut writer, &mut reader, request);
+    }
+
+    // 依序審查差異、回答問題，再處理整體的選項与回覆
+    let Some(review) = review_diff(&mut writer, &mut reader, &request.diff_files)? else {
+        return Ok(PopupOutcome::Response("用户取消了操作".to_string()));
+    };
+
+    let mut answers = Vec::new();
+    for (index, question) in request.questions.iter().enumerate() {
+        writeln!(writer, "【問題 {}/{}】{}", index + 1, request.questions.len(), question.message.trim())?;
+        let Some(selected) = ask_options(&mut writer, &mut reader, &question.predefined_options, question.selection_mode)? else {
+            return Ok(PopupOutcome::Response("用户取消了操作".to_string()));
+        };
+        writeln!(writer, "輸入此題的回覆（可選，可多行，空白行結束）:")?;
+        writer.flush()?;
+        let lines = read_paragraph(&mut reader)?;
+        answers.push(QuestionAnswer::new(&question.id, selected, (!lines.is_empty()).then(|| lines.join("\n"))));
+        writeln!(writer)?;
+    }
+
+    let Some(selected_options) = ask_options(&mut writer, &mut reader, &options, request.selection_mode)? else {
+        return Ok(PopupOutcome::Response("用户取消了操作".to_string()));
+    };
+
+    let allow_quit = options.is_empty() && answers.is_empty() && review.is_empty();
+    if allow_quit {
+        writeln!(writer, "輸入回覆（可多行，空白行結束；輸入 q 取消）:")?;
+    } else if answers.is_empty() && review.is_empty() {
+        writeln!(writer, "輸入回覆（可多行，空白行結束）:")?;
+    } else {
+        writeln!(writer, "輸入補充說明（可選，可多行，空白行結束）:")?;
+    }
+    writer.flush()?;
+    let mut lines = Vec::new();
+    while let Some(line) = read_line(&mut reader)? {
+        if line.is_empty() {
+            break;
+        }
+        if allow_quit && lines.is_empty() && line.eq_ignore_ascii_case("q") {
+            return Ok(PopupOutcome::Response("用户取消了操作".to_string()));
+        }
+        lines.push(line);
+    }
+
+    writeln!(writer, "已送出回覆")?;
+    writer.flush()?;
+
+    let mut user_input = (!lines.is_empty()).then(|| lines.join("\n"));
+    // 与弹窗一致：没有任何內容時視為確認繼續
+    let answered = answers
+        .iter()
+        .any(|answer| !answer.selected_option_ids.is_empty() || answer.user_input.is_some());
+    if user_input.is_none() && selected_options.is_empty() && !answered && review.is_empty() {
+        user_input = Some("用户確認繼續".to_string());
+    }
+
+    let response = McpResponse {
+        user_input,
+        answers,
+        review,
+        ..McpResponse::new(Some(request.id.clone()), "terminal")
+    };
+    Ok(PopupOutcome::Response(response.with_selected_options(selected_options).to_string()))
+}
+
+/// 逐欄位讀取表單值，輸入不符合欄位定義時重新輸入
+fn fill_form(writer: &mut impl Write, reader: &mut impl BufRead, request: &PopupRequest) -> Result<PopupOutcome> {
+    let mut values = serde_json::Map::new();
+    for (index, field) in request.form_fields.iter().enumerate() {
+        let mut title = format!("【{}/{}】{}", index + 1, request.form_fields.len(), field.title);
+        if field.title != field.name {
+            title.push_str(&format!("（{}）", field.name));
+        }
+        if field.required {
+            title.push_str(" *必填");
+        }
+        writeln!(writer, "{}", title)?;
+        if let Some(description) = &field.description {
+            writeln!(writer, "  {}", description)?;
+        }
+        for (option_index, option) in field.options.iter().enumerate() {
+            writeln!(writer, "  {}. {}", option_index + 1, option.label)?;
+        }
+
+        let hint = form_hint(field);
+        loop {
+            write!(writer, "{}: ", hint)?;
+            writer.flush()?;
+
+            let Some(line) = read_line(reader)? else {
+                return Ok(PopupOutcome::Response("用户取消了操作".to_string()));
+            };
+            if line == "q" {
+                return Ok(PopupOutcome::Response("用户取消了操作".to_string()));
+            }
+            match parse_form_input(field, &line).and_then(|value| validate_field(field, value.as_ref())) {
+                Ok(value) => {
+                    if let Some(value) = value {
+                        values.insert(field.name.clone(), value);
+                    }
+                    break;
+                }
+                Err(e) => writeln!(writer, "{}，請重新輸入", e)?,
+            }
+        }
+        writeln!(writer)?;
+    }
+
+    writeln!(writer, "已送出表單")?;
+    writer.flush()?;
+    Ok(PopupOutcome::Response(build_form_response(
+        serde_json::Value::Object(values),
+        Some(request.id.clone()),
+        "terminal",
+    )))
+}
+
+/// 欄位的輸入提示，例如「輸入整數（1 ~ 65535；Enter 使用預設值 8080；q 取消）」
+fn form_hint(field: &FormField) -> String {
+    let mut notes = Vec::new();
+    let action = match (field.kind, field.options.is_empty()) {
+        (FormFieldKind::Array, false) => {
+            notes.push("可多選，以逗號分隔".to_string());
+            "輸入選項編號"
+        }
+        (_, false) => "輸入選項編號",
+        (FormFieldKind::Boolean, _) => "輸入 y 或 n",
+        (FormFieldKind::Integer, _) => "輸入整數",
+        (FormFieldKind::Number, _) => "輸入數字",
+        (FormFieldKind::Array, _) => {
+            notes.push("以逗號分隔".to_string());
+            "輸入多個值"
+        }
+        (FormFieldKind::String, _) => "輸入文字",
+    };
+    if field.minimum.is_some() || field.maximum.is_some() {
+        let bound = |value: Option<f64>| value.map(|v| v.to_string()).unwrap_or_default();
+        notes.push(format!("{} ~ {}", bound(field.minimum), bound(field.maximum)));
+    }
+    match (&field.default, field.required) {
+        (Some(default), _) => notes.push(format!("Enter 使用預設值 {}", display_value(default))),
+        (None, false) => notes.push("Enter 略過".to_string()),
+        (None, true) => {}
+    }
+    notes.push("q 取消".to_string());
+    format!("{}（{}）", action, notes.join("；"))
+}
+
+/// 將終端輸入轉為欄位值，空白輸入傳回 None；有可選值時輸入編號或值本身
+fn parse_form_input(field: &FormField, line: &str) -> Result<Option<serde_json::Value>, String> {
+    if line.is_empty() {
+        return Ok(None);
+    }
+
+    let parse_element = |token: &str, kind: FormFieldKind| -> Result<serde_json::Value, String> {
+        if !field.options.is_empty() {
+            let by_index = token
+                .parse::<usize>()
+                .ok()
+                .and_then(|number| number.checked_sub(1))
+                .and_then(|index| field.options.get(index));
+            return by_index
+                .or_else(|| field.options.iter().find(|option| option.label == token || display_value(&option.value) == token))
+                .map(|option| option.value.clone())
+                .ok_or_else(|| format!("無效的選項：{}", token));
+        }
+        match kind {
+            FormFieldKind::Number | FormFieldKind::Integer => serde_json::from_str::<serde_json::Number>(token)
+                .map(serde_json::Value::Number)
+                .map_err(|_| format!("「{}」不是數字", token)),
+            FormFieldKind::Boolean => match token.to_lowercase().as_str() {
+                "y" | "yes" | "true" | "是" => Ok(serde_json::Value::Bool(true)),
+                "n" | "no" | "false" | "否" => Ok(serde_json::Value::Bool(false)),
+                _ => Err("請輸入 y 或 n".to_string()),
+            },
+            FormFieldKind::String | FormFieldKind::Array => Ok(serde_json::Value::String(token.to_string())),
+        }
+    };
+
+    match field.kind {
+        FormFieldKind::Array => line
+            .split([',', '，'])
+            .map(str::trim)
+            .filter(|token| !token.is_empty())
+            .map(|token| parse_element(token, field.item_kind.unwrap_or(FormFieldKind::String)))
+            .collect::<Result<Vec<_>, _>>()
+            .map(|items| Some(serde_json::Value::Array(items))),
+        kind => parse_element(line, kind).map(Some),
+    }
+}
+
+/// 逐區塊顯示差異並讀取審查判定，使用者取消時傳回 None
+fn review_diff(
+    writer: &mut impl Write,
+    reader: &mut impl BufRead,
+    files: &[DiffFile],
+) -> Result<Option<Vec<FileReview>>> {
+    let mut review = Vec::new();
+    // 輸入 A 後剩餘的區塊全部核准
+    let mut approve_rest = false;
+
+    for file in files {
+        match &file.old_path {
+            Some(old_path) => writeln!(writer, "━━ {} → {} ━━", old_path, file.path)?,
+            None => writeln!(writer, "━━ {} ━━", file.path)?,
+        }
+
+        let mut verdicts = Vec::new();
+        for (index, hunk) in file.hunks.iter().enumerate() {
+            if approve_rest {
+                verdicts.push((ReviewVerdict::Approved, None));
+                continue;
+            }
+
+            writeln!(writer, "[區塊 {}/{}] {}", index + 1, file.hunks.len(), hunk.header)?;
+            for line in &hunk.lines {
+                writeln!(writer, "{}", line)?;
+            }
+            write!(writer, "審查此區塊（a 核准 / r 拒絕，可在後面加上意見；直接輸入文字為意見；A 核准其餘全部；Enter 略過，q 取消）: ")?;
+            writer.flush()?;
+
+            let Some(line) = read_line(reader)? else {
+                return Ok(None);
+            };
+            if line == "q" {
+                return Ok(None);
+            }
+            if line == "A" {
+                approve_rest = true;
+                verdicts.push((ReviewVerdict::Approved, None));
+                continue;
+            }
+            verdicts.push(parse_verdict(&line));
+        }
+
+        writeln!(writer, "對 {} 的整體意見（可選，Enter 略過）:", file.path)?;
+        writer.flush()?;
+        let comment = read_line(reader)?.filter(|line| !line.is_empty());
+        review.push(FileReview::new(file, comment, verdicts));
+        writeln!(writer)?;
+    }
+
+    Ok(Some(review))
+}
+
+/// 解析區塊審查輸入，例如「a」、「r 請改名」或單純的意見
+fn parse_verdict(line: &str) -> (ReviewVerdict, Option<String>) {
+    let (command, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+    let comment = Some(rest.trim().to_string()).filter(|text| !text.is_empty());
+    match command {
+        "" => (ReviewVerdict::Pending, None),
+        "a" => (ReviewVerdict::Approved, comment),
+        "r" => (ReviewVerdict::Rejected, comment),
+        _ => (ReviewVerdict::Commented, Some(line.to_string())),
+    }
+}
+
+/// 列出選項並讀取選擇，沒有選項時傳回空列表，使用者取消時傳回 None
+fn ask_options(
+    writer: &mut impl Write,
+    reader: &mut impl BufRead,
+    options: &[PredefinedOption],
+    mode: SelectionMode,
+) -> Result<Option<Vec<PredefinedOption>>> {
+    if options.is_empty() {
+        return Ok(Some(Vec::new()));
+    }
+
+    writeln!(writer, "選項：")?;
+    for (index, option) in options.iter().enumerate() {
+        let mut line = format!("  {}. {}", index + 1, option.label);
+        if option.recommended {
+            line.push_str(" ★建議");
+        }
+        if option.exclusive && mode == SelectionMode::Multiple {
+            line.push_str("（不可與其他選項同時選擇）");
+        }
+        writeln!(writer, "{}", line)?;
+        if let Some(description) = &option.description {
+            writeln!(writer, "     {}", description)?;
+        }
+    }
+    let hint = match mode {
+        SelectionMode::Single => "輸入選項編號（單選；直接 Enter 略過，q 取消）: ",
+        SelectionMode::Multiple => "輸入選項編號（可多選，以逗號或空白分隔；直接 Enter 略過，q 取消）: ",
+    };
+
+    loop {
+        write!(writer, "{}", hint)?;
+        writer.flush()?;
+
+        let Some(line) = read_line(reader)? else {
+            return Ok(None);
+        };
+        if line.eq_ignore_ascii_case("q") {
+            return Ok(None);
+        }
+        match parse_selection(&line, options, mode) {
+            Some(selected) => return Ok(Some(selected)),
+            None => writeln!(writer, "無效的選項編號，請重新輸入")?,
+        }
+    }
+}
+
+/// 讀取多行文字直到空白行或 EOF
+fn read_paragraph(reader: &mut impl BufRead) -> Result<Vec<String>> {
+    let mut lines = Vec::new();
+    while let Some(line) = read_line(reader)? {
+        if line.is_empty() {
+            break;
+        }
+        lines.push(line);
+    }
+    Ok(lines)
+}
+
+/// 組合請求來源說明，如「Claude Code · cunzhi (main)」
+fn origin_line(request: &PopupRequest) -> Option<String> {
+    let mut parts: Vec<String> = [&request.client_name, &request.project_name]
+        .into_iter()
+        .flatten()
+        .cloned()
+        .collect();
+    if let (Some(branch), Some(last)) = (&request.git_branch, parts.last_mut()) {
+        last.push_str(&format!(" ({})", branch));
+    }
+    (!parts.is_empty()).then(|| format!("來自 {}", parts.join(" · ")))
+}
+
+/// 讀取一行並去除行尾換行，EOF 時傳回 None
+fn read_line(reader: &mut impl BufRead) -> Result<Option<String>> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    Ok(Some(line.trim_end_matches(['\r', '\n']).trim().to_string()))
+}
+
+/// 解析選項編號，任何編號無效或違反單選、互斥限制時傳回 None
+fn parse_selection(line: &str, options: &[PredefinedOption], mode: SelectionMode) -> Option<Vec<PredefinedOption>> {
+    let mut selected: Vec<PredefinedOption> = Vec::new();
+    for token in line.split(|c: char| c == ',' || c == '，' || c.is_whitespace()) {
+        if token.is_empty() {
+            continue;
+        }
+        let index = token.parse::<usize>().ok()?.checked_sub(1)?;
+        let option = options.get(index)?;
+        if !selected.contains(option) {
+            selected.push(option.clone());
+        }
+    }
+
+    let has_exclusive = selected.iter().any(|option| option.exclusive);
+    if selected.len() > 1 && (mode == SelectionMode::Single || has_exclusive) {
+        return None;
+    }
+    Some(selected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn selected_ids(line: &str, mode: SelectionMode) -> Option<Vec<String>> {
+        let options: Vec<PredefinedOption> =
+            serde_json::from_value(serde_json::json!(["甲", "乙", { "label": "都不要", "exclusive": true }])).unwrap();
+        parse_selection(line, &options, mode).map(|selected| selected.into_iter().map(|option| option.id).collect())
+    }
+
+    #[test]
+    fn test_parse_selection() {
+        // 逗號、全形逗號与空白皆可分隔，重複的編號只算一次
+        assert_eq!(selected_ids("1，2 1", SelectionMode::Multiple), Some(vec!["甲".to_string(), "乙".to_string()]));
+        assert_eq!(selected_ids("", SelectionMode::Multiple), Some(vec![]));
+
+        // 超出範圍或不是編號
+        assert_eq!(selected_ids("0", SelectionMode::Multiple), None);
+        assert_eq!(selected_ids("4", SelectionMode::Multiple), None);
+        assert_eq!(selected_ids("甲", SelectionMode::Multiple), None);
+
+        // 單選只能選一個
+        assert_eq!(selected_ids("2", SelectionMode::Single), Some(vec!["乙".to_string()]));
+        assert_eq!(selected_ids("1,2", SelectionMode::Single), None);
+
+        // 互斥選項不能与其他選項同時選擇
+        assert_eq!(selected_ids("3", SelectionMode::Multiple), Some(vec!["都不要".to_string()]));
+        assert_eq!(selected_ids("1 3", SelectionMode::Multiple), None);
+    }
+}
@@ -1,12 +1,19 @@
 pub mod commands;
+pub mod config_watcher;
+pub mod prompts;
+pub mod resources;
 pub mod server;
+pub mod transport;
 pub mod tools;
 pub mod types;
 pub mod handlers;
 pub mod utils;
 
 pub use commands::*;
+pub use config_watcher::*;
+pub use resources::*;
 pub use server::*;
+pub use transport::*;
 pub use tools::*;
 pub use types::*;
 pub use handlers::*;
@@ -0,0 +1,156 @@
+//! 自訂提示詞 MCP Prompts
+//!
+//! 將設定中的 `CustomPrompt` 以 `prompts/list`、`prompts/get` 公開，
+//! 讓客戶端能以斜線命令使用與彈窗按鈕相同的提示詞
+
+use rmcp::{model::*, ErrorData as McpError};
+
+use crate::config::{CustomPrompt, CustomPromptConfig};
+
+/// 條件性提示詞的布林參數名稱
+pub const CONDITION_ARGUMENT: &str = "enabled";
+
+/// 列出所有可用的自訂提示詞
+///
+/// 自訂提示詞功能停用時傳回空列表；內容為空的普通提示詞（例如清空輸入框）沒有意義，會被略過
+pub fn list_prompts(config: &CustomPromptConfig) -> Vec<Prompt> {
+    if !config.enabled {
+        return Vec::new();
+    }
+
+    let mut prompts: Vec<&CustomPrompt> = config
+        .prompts
+        .iter()
+        .filter(|prompt| is_available(prompt))
+        .collect();
+    prompts.sort_by_key(|prompt| prompt.sort_order);
+
+    prompts.into_iter().map(to_mcp_prompt).collect()
+}
+
+/// 依名稱取得自訂提示詞內容，与列表一致略過內容為空的普通提示詞
+pub fn get_prompt(
+    config: &CustomPromptConfig,
+    name: &str,
+    arguments: Option<&JsonObject>,
+) -> Result<GetPromptResult, McpError> {
+    let prompt = config
+        .prompts
+        .iter()
+        .find(|prompt| prompt.id == name)
+        .filter(|prompt| config.enabled && is_available(prompt))
+        .ok_or_else(|| McpError::invalid_params(format!("未知的提示詞: {}", name), None))?;
+
+    let text = if is_conditional(prompt) {
+        let state = match arguments.and_then(|args| args.get(CONDITION_ARGUMENT)) {
+            Some(value) => parse_bool_argument(value).ok_or_else(|| {
+                McpError::invalid_params(
+                    format!("參數 {} 必須為 true 或 false，收到: {}", CONDITION_ARGUMENT, value),
+                    None,
+                )
+            })?,
+            None => prompt.current_state,
+        };
+
+        let template = if state { &prompt.template_true } else { &prompt.template_false };
+        template.clone().unwrap_or_default()
+    } else {
+        prompt.content.clone()
+    };
+
+    Ok(GetPromptResult {
+        description: prompt.description.clone(),
+        messages: vec![PromptMessage::new_text(PromptMessageRole::User, text)],
+    })
+}
+
+fn to_mcp_prompt(prompt: &CustomPrompt) -> Prompt {
+    let arguments = is_conditional(prompt).then(|| {
+        let condition = prompt
+            .condition_text
+            .clone()
+            .unwrap_or_else(|| prompt.name.clone());
+        vec![PromptArgument {
+            name: CONDITION_ARGUMENT.to_string(),
+            title: Some(condition.clone()),
+            description: Some(format!(
+                "{}（true/false，未提供時使用目前設定：{}）",
+                condition, prompt.current_state
+            )),
+            required: Some(false),
+        }]
+    });
+
+    let mut mcp_prompt = Prompt::new(prompt.id.clone(), prompt.description.clone(), arguments);
+    mcp_prompt.title = Some(prompt.name.clone());
+    mcp_prompt
+}
+
+fn is_conditional(prompt: &CustomPrompt) -> bool {
+    prompt.r#type == "conditional"
+}
+
+/// 是否公開給客戶端：條件性提示詞，或內容不為空的普通提示詞
+fn is_available(prompt: &CustomPrompt) -> bool {
+    is_conditional(prompt) || !prompt.content.trim().is_empty()
+}
+
+/// 解析布林參數，接受 JSON 布林值與常見的字串寫法
+fn parse_bool_argument(value: &serde_json::Value) -> Option<bool> {
+    match value {
+        serde_json::Value::Bool(flag) => Some(*flag),
+        serde_json::Value::String(text) => match text.trim().to_lowercase().as_str() {
+            "true" | "1" | "yes" | "on" | "是" => Some(true),
+            "false" | "0" | "no" | "off" | "否" => Some(false),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn prompt(id: &str, content: &str) -> CustomPrompt {
+        serde_json::from_value(json!({
+            "id": id,
+            "name": id,
+            "content": content,
+            "description": null,
+            "sort_order": 0,
+            "created_at": "",
+            "updated_at": "",
+            "condition_text": null,
+            "template_true": null,
+            "template_false": null,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_empty_prompts_are_hidden() {
+        let config = CustomPromptConfig {
+            prompts: vec![prompt("review", "請審查程式碼"), prompt("clear", "  ")],
+            enabled: true,
+            max_prompts: 50,
+        };
+
+        let names: Vec<String> = list_prompts(&config).into_iter().map(|prompt| prompt.name).collect();
+        assert_eq!(names, vec!["review"]);
+        assert!(get_prompt(&config, "review", None).is_ok());
+        assert!(get_prompt(&config, "clear", None).is_err());
+    }
+
+    #[test]
+    fn test_parse_bool_argument() {
+        assert_eq!(parse_bool_argument(&json!(true)), Some(true));
+        assert_eq!(parse_bool_argument(&json!(" Yes ")), Some(true));
+        assert_eq!(parse_bool_argument(&json!("是")), Some(true));
+        assert_eq!(parse_bool_argument(&json!("OFF")), Some(false));
+        assert_eq!(parse_bool_argument(&json!("0")), Some(false));
+        assert_eq!(parse_bool_argument(&json!("maybe")), None);
+        assert_eq!(parse_bool_argument(&json!(1)), None);
+    }
+}
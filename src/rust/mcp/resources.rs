@@ -0,0 +1,268 @@
+//! 專案記憶 MCP 資源
+//!
+//! 將 `.cunzhi-memory` 下的各分類檔案以 `cunzhi-memory://<專案>/<分類>` 形式公開，
+//! 支援 `resources/list`、`resources/read` 與檔案變更時的訂閱通知
+
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, CONTROLS};
+use rmcp::{
+    model::*,
+    ErrorData as McpError, Peer, RoleServer,
+};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use super::tools::memory::{MemoryCategory, MemoryManager};
+use super::utils::file_modified_time;
+use crate::constants::mcp::{FILE_WATCH_INTERVAL_MS, MEMORY_DIR_NAME, MEMORY_RESOURCE_SCHEME};
+use crate::{log_debug, log_important};
+
+/// URI 中專案名稱需要編碼的字元
+const PROJECT_NAME_ENCODE_SET: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'/')
+    .add(b'?')
+    .add(b'#')
+    .add(b'%');
+
+/// 專案記憶資源管理器（每個 MCP 工作階段一份）
+#[derive(Default)]
+pub struct MemoryResources {
+    /// 專案名稱 -> git 根目錄
+    projects: Mutex<BTreeMap<String, PathBuf>>,
+    /// 已訂閱的資源 URI -> 上次觀察到的修改時間
+    subscriptions: Mutex<HashMap<String, Option<SystemTime>>>,
+    /// 用於傳送通知的客戶端
+    peer: Mutex<Option<Peer<RoleServer>>>,
+    watcher_started: AtomicBool,
+}
+
+impl MemoryResources {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 綁定客戶端，之後的資源變更會通知該客戶端
+    pub fn attach_peer(&self, peer: Peer<RoleServer>) {
+        if let Ok(mut guard) = self.peer.lock() {
+            *guard = Some(peer);
+        }
+    }
+
+    /// 註冊專案路徑（工作目錄、客戶端 roots 或 ji 呼叫的 project_path）
+    ///
+    /// 僅 git 專案會被註冊；新增專案時會通知客戶端資源列表已變更
+    pub fn register_project(&self, project_path: &str) {
+        let Ok(root) = MemoryManager::resolve_project_root(project_path) else {
+            log_debug!("略過非 git 專案路徑: {}", project_path);
+            return;
+        };
+
+        let added = {
+            let Ok(mut projects) = self.projects.lock() else {
+                return;
+            };
+            if projects.values().any(|existing| existing == &root) {
+                false
+            } else {
+                let name = unique_project_name(&projects, &root);
+                log_debug!("註冊記憶資源專案: {} -> {:?}", name, root);
+                projects.insert(name, root);
+                true
+            }
+        };
+
+        if added {
+            if let Some(peer) = self.current_peer() {
+                tokio::spawn(async move {
+                    let _ = peer.notify_resource_list_changed().await;
+                });
+            }
+        }
+    }
+
+    /// 註冊客戶端提供的 roots（僅支援 file:// URI，路徑解碼由 register_project 處理）
+    pub fn register_roots(&self, roots: &[Root]) {
+        for root in roots {
+            if let Some(path) = root.uri.strip_prefix("file://") {
+                self.register_project(path);
+            }
+        }
+    }
+
+    /// 列出所有已存在記憶目錄的專案分類資源
+    pub fn list(&self) -> Vec<Resource> {
+        let Ok(projects) = self.projects.lock() else {
+            return Vec::new();
+        };
+
+        let mut resources = Vec::new();
+        for (name, root) in projects.iter() {
+            let memory_dir = root.join(MEMORY_DIR_NAME);
+            if !memory_dir.is_dir() {
+                continue;
+            }
+
+            for category in MemoryCategory::ALL {
+                let file_path = memory_dir.join(category.file_name());
+                let mut resource = RawResource::new(resource_uri(name, category), format!("{}/{}", name, category.slug()));
+                resource.title = Some(format!("{} - {}", name, category.title()));
+                resource.description = Some(format!("{} 專案的{}", name, category.title()));
+                resource.mime_type = Some("text/markdown".to_string());
+                resource.size = std::fs::metadata(&file_path).ok().map(|metadata| metadata.len() as u32);
+                resources.push(resource.no_annotation());
+            }
+        }
+
+        resources
+    }
+
+    /// 讀取資源內容
+    pub fn read(&self, uri: &str) -> Result<ReadResourceResult, McpError> {
+        let file_path = self.resolve_uri(uri)?;
+        let content = if file_path.exists() {
+            std::fs::read_to_string(&file_path)
+                .map_err(|e| McpError::internal_error(format!("讀取記憶檔案失敗: {}", e), None))?
+        } else {
+            String::new()
+        };
+
+        Ok(ReadResourceResult {
+            contents: vec![ResourceContents::TextResourceContents {
+                uri: uri.to_string(),
+                mime_type: Some("text/markdown".to_string()),
+                text: content,
+                meta: None,
+            }],
+        })
+    }
+
+    /// 訂閱資源變更
+    pub fn subscribe(self: &Arc<Self>, uri: &str) -> Result<(), McpError> {
+        let file_path = self.resolve_uri(uri)?;
+        if let Ok(mut subscriptions) = self.subscriptions.lock() {
+            subscriptions.insert(uri.to_string(), file_modified_time(&file_path));
+        }
+        self.ensure_watcher();
+        Ok(())
+    }
+
+    /// 取消訂閱資源變更
+    pub fn unsubscribe(&self, uri: &str) {
+        if let Ok(mut subscriptions) = self.subscriptions.lock() {
+            subscriptions.remove(uri);
+        }
+    }
+
+    /// 啟動輪詢任務，偵測已訂閱檔案的修改並傳送 `notifications/resources/updated`
+    fn ensure_watcher(self: &Arc<Self>) {
+        if self.watcher_started.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        let resources = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_millis(FILE_WATCH_INTERVAL_MS));
+            loop {
+                interval.tick().await;
+
+                let Some(peer) = resources.current_peer() else {
+                    continue;
+                };
+                if peer.is_transport_closed() {
+                    break;
+                }
+
+                for uri in resources.changed_subscriptions() {
+                    log_debug!("記憶資源已變更: {}", uri);
+                    if let Err(e) = peer
+                        .notify_resource_updated(ResourceUpdatedNotificationParam { uri })
+                        .await
+                    {
+                        log_important!(warn, "傳送資源更新通知失敗: {}", e);
+                    }
+                }
+            }
+            resources.watcher_started.store(false, Ordering::SeqCst);
+        });
+    }
+
+    /// 找出修改時間與上次記錄不同的訂閱，並更新記錄
+    fn changed_subscriptions(&self) -> Vec<String> {
+        let uris: Vec<String> = match self.subscriptions.lock() {
+            Ok(subscriptions) => subscriptions.keys().cloned().collect(),
+            Err(_) => return Vec::new(),
+        };
+
+        let mut changed = Vec::new();
+        for uri in uris {
+            let Ok(file_path) = self.resolve_uri(&uri) else {
+                continue;
+            };
+            let modified = file_modified_time(&file_path);
+            if let Ok(mut subscriptions) = self.subscriptions.lock() {
+                if let Some(last_modified) = subscriptions.get_mut(&uri) {
+                    if *last_modified != modified {
+                        *last_modified = modified;
+                        changed.push(uri);
+                    }
+                }
+            }
+        }
+        changed
+    }
+
+    /// 將資源 URI 解析為記憶檔案路徑
+    fn resolve_uri(&self, uri: &str) -> Result<PathBuf, McpError> {
+        let invalid = || McpError::resource_not_found(format!("未知的記憶資源: {}", uri), None);
+
+        let rest = uri
+            .strip_prefix(MEMORY_RESOURCE_SCHEME)
+            .and_then(|rest| rest.strip_prefix("://"))
+            .ok_or_else(invalid)?;
+        let (encoded_name, slug) = rest.rsplit_once('/').ok_or_else(invalid)?;
+        let name = percent_decode_str(encoded_name).decode_utf8_lossy();
+        let category = MemoryCategory::from_slug(slug).ok_or_else(invalid)?;
+
+        let projects = self
+            .projects
+            .lock()
+            .map_err(|e| McpError::internal_error(format!("讀取專案列表失敗: {}", e), None))?;
+        let root = projects.get(name.as_ref()).ok_or_else(invalid)?;
+
+        Ok(root.join(MEMORY_DIR_NAME).join(category.file_name()))
+    }
+
+    fn current_peer(&self) -> Option<Peer<RoleServer>> {
+        self.peer.lock().ok().and_then(|guard| guard.clone())
+    }
+}
+
+/// 產生記憶資源 URI
+fn resource_uri(project_name: &str, category: MemoryCategory) -> String {
+    format!(
+        "{}://{}/{}",
+        MEMORY_RESOURCE_SCHEME,
+        utf8_percent_encode(project_name, PROJECT_NAME_ENCODE_SET),
+        category.slug()
+    )
+}
+
+/// 以目錄名稱作為專案名稱，重名時附加序號
+fn unique_project_name(projects: &BTreeMap<String, PathBuf>, root: &Path) -> String {
+    let base = root
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| "project".to_string());
+
+    let mut name = base.clone();
+    let mut index = 2;
+    while projects.contains_key(&name) {
+        name = format!("{}-{}", base, index);
+        index += 1;
+    }
+    name
+}
+
@@ -1,20 +1,29 @@
 use anyhow::Result;
 use rmcp::{
-    ErrorData as McpError, ServerHandler, ServiceExt, RoleServer,
+    ErrorData as McpError, Peer, ServerHandler, ServiceExt, RoleServer,
     model::*,
     transport::stdio,
-    service::RequestContext,
+    service::{NotificationContext, RequestContext},
 };
-use std::collections::HashMap;
+use serde::de::DeserializeOwned;
+use std::sync::Arc;
 
+use super::config_watcher::ConfigCache;
+use super::prompts;
+use super::resources::MemoryResources;
 use super::tools::{InteractionTool, MemoryTool};
-use super::types::{ZhiRequest, JiyiRequest};
-use crate::config::load_standalone_config;
+use super::transport::{serve_http, TransportKind, TransportOptions};
+use super::types::{ZhiRequest, ZhiFormRequest, ZhiPollRequest, ZhiWaitRequest, ZhiNotifyRequest, JiyiRequest, McpResponse};
+use super::utils::{generate_request_id, schema_object_for};
+use crate::constants::mcp::{TOOL_JI, TOOL_ZHI_ASK, TOOL_ZHI_FORM, TOOL_ZHI_NOTIFY};
 use crate::{log_important, log_debug};
 
 #[derive(Clone)]
 pub struct ZhiServer {
-    enabled_tools: HashMap<String, bool>,
+    config: Arc<ConfigCache>,
+    memory_resources: Arc<MemoryResources>,
+    /// 工作階段 ID，`zhi_notify` 依此分開記錄各工作階段的通知
+    session_id: Arc<str>,
 }
 
 impl Default for ZhiServer {
@@ -25,32 +34,45 @@ impl Default for ZhiServer {
 
 impl ZhiServer {
     pub fn new() -> Self {
-        // 嘗試載入設定，如果失敗则使用預設設定
-        let enabled_tools = match load_standalone_config() {
-            Ok(config) => config.mcp_config.tools,
-            Err(e) => {
-                log_important!(warn, "無法載入設定檔案，使用預設工具設定: {}", e);
-                crate::config::default_mcp_tools()
-            }
-        };
+        Self::with_config(Arc::new(ConfigCache::new()))
+    }
 
-        Self { enabled_tools }
+    /// 使用共用的設定快取建立服务器（HTTP 模式下所有工作階段共用）
+    pub fn with_config(config: Arc<ConfigCache>) -> Self {
+        Self {
+            config,
+            memory_resources: Arc::new(MemoryResources::new()),
+            session_id: generate_request_id().into(),
+        }
     }
 
-    /// 檢查工具是否啟用 - 動態讀取最新設定
+    /// 檢查工具是否啟用 - 讀取快取設定，設定檔變更時由監看任務自動更新
     fn is_tool_enabled(&self, tool_name: &str) -> bool {
-        // 每次都重新讀取設定，确保獲取最新狀態
-        match load_standalone_config() {
-            Ok(config) => {
-                let enabled = config.mcp_config.tools.get(tool_name).copied().unwrap_or(true);
-                log_debug!("工具 {} 當前狀態: {}", tool_name, enabled);
-                enabled
-            }
-            Err(e) => {
-                log_important!(warn, "讀取設定失敗，使用快取狀態: {}", e);
-                // 如果讀取失敗，使用快取的設定
-                self.enabled_tools.get(tool_name).copied().unwrap_or(true)
-            }
+        self.config.is_tool_enabled(tool_name)
+    }
+
+    /// 工具已在設定中停用時傳回錯誤
+    fn ensure_tool_enabled(&self, tool_name: &str) -> Result<(), McpError> {
+        if self.is_tool_enabled(tool_name) {
+            Ok(())
+        } else {
+            Err(McpError::internal_error(format!("{} 工具已被禁用", tool_name), None))
+        }
+    }
+
+    /// 向客戶端查詢 roots 並註冊為記憶資源專案
+    async fn refresh_roots(&self, peer: &Peer<RoleServer>) {
+        let supports_roots = peer
+            .peer_info()
+            .map(|info| info.capabilities.roots.is_some())
+            .unwrap_or(false);
+        if !supports_roots {
+            return;
+        }
+
+        match peer.list_roots().await {
+            Ok(result) => self.memory_resources.register_roots(&result.roots),
+            Err(e) => log_important!(warn, "獲取客戶端 roots 失敗: {}", e),
         }
     }
 }
@@ -58,8 +80,16 @@ impl ZhiServer {
 impl ServerHandler for ZhiServer {
     fn get_info(&self) -> ServerInfo {
         ServerInfo {
-            protocol_version: ProtocolVersion::V_2024_11_05,
-            capabilities: ServerCapabilities::builder().enable_tools().build(),
+            protocol_version: ProtocolVersion::V_2025_06_18,
+            capabilities: ServerCapabilities::builder()
+                .enable_prompts()
+                .enable_prompts_list_changed()
+                .enable_resources()
+                .enable_resources_subscribe()
+                .enable_resources_list_changed()
+                .enable_tools()
+                .enable_tool_list_changed()
+                .build(),
             server_info: Implementation {
                 name: "Zhi-mcp".to_string(),
                 version: env!("CARGO_PKG_VERSION").to_string(),
@@ -67,97 +97,187 @@ impl ServerHandler for ZhiServer {
                 website_url: Some("https://github.com/zoonderkins/cunzhi".to_string()),
                 icons: None,
             },
-            instructions: Some("Zhi 智慧程式碼審查工具，支援互動式對話和記憶管理\n\n可用工具：\n- zhi: 互動式審查工具，支援預定義選項、自由輸入和圖片上傳\n- ji: 記憶管理工具（可選），用於儲存專案規範和偏好\n\n常見回應選項：confirm（確認）、reject（拒絕）、discuss（討論）等".to_string()),
+            instructions: Some("Zhi 智慧程式碼審查工具，支援互動式對話和記憶管理\n\n可用工具：\n- zhi: 互動式審查工具，支援預定義選項、自由輸入、圖片上傳、多個問題、差異審查与附件\n- zhi_form（可選）: 依 JSON Schema 顯示表單，傳回驗證後的結構化值\n- zhi_ask / zhi_poll / zhi_wait（可選）: 非阻塞詢問，先取得票證，之後查詢或等待回覆\n- zhi_notify（可選）: 不需要回覆的進度通知\n- ji: 記憶管理工具（可選），用於儲存專案規範和偏好，支援全域記憶与依 query 檢索\n\n專案記憶同時以 cunzhi-memory://<專案>/<分類> 資源公開，自訂提示詞以 MCP prompts 公開\n\n常見回應選項：confirm（確認）、reject（拒絕）、discuss（討論）等".to_string()),
         }
     }
 
     async fn initialize(
         &self,
-        _request: InitializeRequestParam,
-        _context: RequestContext<RoleServer>,
+        request: InitializeRequestParam,
+        context: RequestContext<RoleServer>,
     ) -> Result<ServerInfo, McpError> {
+        // 記錄客戶端能力，zhi 據此決定能否使用 elicitation
+        log_debug!(
+            "客戶端 {} 支援 elicitation: {}",
+            request.client_info.name,
+            request.capabilities.elicitation.is_some()
+        );
+        if context.peer.peer_info().is_none() {
+            context.peer.set_peer_info(request);
+        }
         Ok(self.get_info())
     }
 
+    async fn on_initialized(&self, context: NotificationContext<RoleServer>) {
+        self.config.attach_peer(context.peer.clone());
+        self.memory_resources.attach_peer(context.peer.clone());
+
+        // 工作目錄通常就是客戶端開啟的專案
+        if let Ok(cwd) = std::env::current_dir() {
+            self.memory_resources.register_project(&cwd.to_string_lossy());
+        }
+
+        self.refresh_roots(&context.peer).await;
+    }
+
+    async fn on_roots_list_changed(&self, context: NotificationContext<RoleServer>) {
+        self.refresh_roots(&context.peer).await;
+    }
+
+    async fn list_prompts(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListPromptsResult, McpError> {
+        let config = self.config.current();
+
+        Ok(ListPromptsResult {
+            prompts: prompts::list_prompts(&config.custom_prompt_config),
+            next_cursor: None,
+        })
+    }
+
+    async fn get_prompt(
+        &self,
+        request: GetPromptRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<GetPromptResult, McpError> {
+        let config = self.config.current();
+
+        prompts::get_prompt(&config.custom_prompt_config, &request.name, request.arguments.as_ref())
+    }
+
+    async fn list_resources(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListResourcesResult, McpError> {
+        Ok(ListResourcesResult {
+            resources: self.memory_resources.list(),
+            next_cursor: None,
+        })
+    }
+
+    async fn read_resource(
+        &self,
+        request: ReadResourceRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ReadResourceResult, McpError> {
+        self.memory_resources.read(&request.uri)
+    }
+
+    async fn subscribe(
+        &self,
+        request: SubscribeRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<(), McpError> {
+        self.memory_resources.subscribe(&request.uri)
+    }
+
+    async fn unsubscribe(
+        &self,
+        request: UnsubscribeRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<(), McpError> {
+        self.memory_resources.unsubscribe(&request.uri);
+        Ok(())
+    }
+
     async fn list_tools(
         &self,
         _request: Option<PaginatedRequestParam>,
         _context: RequestContext<RoleServer>,
     ) -> Result<ListToolsResult, McpError> {
-        use std::sync::Arc;
         use std::borrow::Cow;
 
-        let mut tools = Vec::new();
-
-        // 寸止工具始终可用（必需工具）
-        let zhi_schema = serde_json::json!({
-            "type": "object",
-            "properties": {
-                "message": {
-                    "type": "string",
-                    "description": "要顯示給使用者的訊息"
-                },
-                "predefined_options": {
-                    "type": "array",
-                    "items": {"type": "string"},
-                    "description": "預定義的選項列表（可選）"
-                },
-                "is_markdown": {
-                    "type": "boolean",
-                    "description": "訊息是否為Markdown格式，預設為true"
-                }
-            },
-            "required": ["message"]
-        });
+        // 寸止工具始终可用（必需工具），其余工具依設定啟用；Schema 由請求/回應類型推導
+        let mut tools = vec![Tool {
+            name: Cow::Borrowed("zhi"),
+            title: Some("寸止互動工具".to_string()),
+            description: Some(Cow::Borrowed("智慧程式碼審查互動工具，支援預定義選項、自由文字輸入和圖片上傳")),
+            input_schema: schema_object_for::<ZhiRequest>(),
+            output_schema: Some(schema_object_for::<McpResponse>()),
+            icons: None,
+            annotations: None,
+        }];
 
-        if let serde_json::Value::Object(schema_map) = zhi_schema {
+        if self.is_tool_enabled(TOOL_ZHI_FORM) {
+            // 表單工具与寸止共用互動方式；輸出 Schema 由請求中的 schema 決定，因此不固定宣告
             tools.push(Tool {
-                name: Cow::Borrowed("zhi"),
-                title: Some("寸止互動工具".to_string()),
-                description: Some(Cow::Borrowed("智慧程式碼審查互動工具，支援預定義選項、自由文字輸入和圖片上傳")),
-                input_schema: Arc::new(schema_map),
+                name: Cow::Borrowed("zhi_form"),
+                title: Some("寸止表單工具".to_string()),
+                description: Some(Cow::Borrowed("依 JSON Schema 顯示表單讓使用者填寫，例如連接埠、名稱与功能開關；送出的值經過驗證後以 structuredContent 傳回")),
+                input_schema: schema_object_for::<ZhiFormRequest>(),
                 output_schema: None,
                 icons: None,
                 annotations: None,
             });
         }
 
-        // 記憶管理工具 - 僅在啟用時新增
-        if self.is_tool_enabled("ji") {
-            let ji_schema = serde_json::json!({
-                "type": "object",
-                "properties": {
-                    "action": {
-                        "type": "string",
-                        "description": "操作類型：記憶(新增記憶), 回憶(獲取專案訊息)"
-                    },
-                    "project_path": {
-                        "type": "string",
-                        "description": "專案路徑（必需）"
-                    },
-                    "content": {
-                        "type": "string",
-                        "description": "記憶內容（記憶操作時必需）"
-                    },
-                    "category": {
-                        "type": "string",
-                        "description": "記憶分類：rule(規範規則), preference(使用者偏好), pattern(最佳實務), context(專案上下文)"
-                    }
-                },
-                "required": ["action", "project_path"]
+        // zhi_poll 与 zhi_wait 只處理 zhi_ask 的票證，隨 zhi_ask 一起啟用
+        if self.is_tool_enabled(TOOL_ZHI_ASK) {
+            tools.push(Tool {
+                name: Cow::Borrowed("zhi_ask"),
+                title: Some("寸止非阻塞詢問".to_string()),
+                description: Some(Cow::Borrowed("參數与 zhi 相同，但不等待回覆：在背景顯示弹窗並立即傳回票證 ID，之後以 zhi_poll 查詢或 zhi_wait 等待回覆；票證在等一下重新啟動後仍會保留")),
+                input_schema: schema_object_for::<ZhiRequest>(),
+                output_schema: None,
+                icons: None,
+                annotations: None,
             });
+            tools.push(Tool {
+                name: Cow::Borrowed("zhi_poll"),
+                title: Some("查詢寸止票證".to_string()),
+                description: Some(Cow::Borrowed("查詢 zhi_ask 票證，已回覆時傳回与 zhi 相同的結果，否則傳回 pending 狀態")),
+                input_schema: schema_object_for::<ZhiPollRequest>(),
+                output_schema: None,
+                icons: None,
+                annotations: None,
+            });
+            tools.push(Tool {
+                name: Cow::Borrowed("zhi_wait"),
+                title: Some("等待寸止票證".to_string()),
+                description: Some(Cow::Borrowed("等待 zhi_ask 票證的回覆，最多等待 timeout_seconds 秒；已回覆時傳回与 zhi 相同的結果")),
+                input_schema: schema_object_for::<ZhiWaitRequest>(),
+                output_schema: None,
+                icons: None,
+                annotations: None,
+            });
+        }
 
-            if let serde_json::Value::Object(schema_map) = ji_schema {
-                tools.push(Tool {
-                    name: Cow::Borrowed("ji"),
-                    title: Some("記憶管理工具".to_string()),
-                    description: Some(Cow::Borrowed("全域記憶管理工具，用於儲存和管理重要的開發規範、使用者偏好和最佳實務")),
-                    input_schema: Arc::new(schema_map),
-                    output_schema: None,
-                    icons: None,
-                    annotations: None,
-                });
-            }
+        if self.is_tool_enabled(TOOL_ZHI_NOTIFY) {
+            tools.push(Tool {
+                name: Cow::Borrowed("zhi_notify"),
+                title: Some("寸止進度通知".to_string()),
+                description: Some(Cow::Borrowed("發出不需要回覆的進度或狀態通知（例如「步驟 3/7：執行資料庫遷移」）並立即傳回；常駐的等一下會更新系統匣並顯示提示，可選擇播放提示音，最近的通知可在等一下的通知紀錄中查看")),
+                input_schema: schema_object_for::<ZhiNotifyRequest>(),
+                output_schema: None,
+                icons: None,
+                annotations: None,
+            });
+        }
+
+        // 記憶管理工具 - 僅在啟用時新增
+        if self.is_tool_enabled(TOOL_JI) {
+            tools.push(Tool {
+                name: Cow::Borrowed("ji"),
+                title: Some("記憶管理工具".to_string()),
+                description: Some(Cow::Borrowed("全域記憶管理工具，用於儲存和管理重要的開發規範、使用者偏好和最佳實務；scope 為 global 的記憶跨專案共用，適合個人偏好")),
+                input_schema: JiyiRequest::input_schema(),
+                output_schema: None,
+                icons: None,
+                annotations: None,
+            });
         }
 
         log_debug!("傳回給客戶端的工具列表: {:?}", tools.iter().map(|t| &t.name).collect::<Vec<_>>());
@@ -171,42 +291,51 @@ impl ServerHandler for ZhiServer {
     async fn call_tool(
         &self,
         request: CallToolRequestParam,
-        _context: RequestContext<RoleServer>,
+        context: RequestContext<RoleServer>,
     ) -> Result<CallToolResult, McpError> {
         log_debug!("收到工具呼叫請求: {}", request.name);
 
         match request.name.as_ref() {
             "zhi" => {
-                // 解析請求參數
-                let arguments_value = request.arguments
-                    .map(serde_json::Value::Object)
-                    .unwrap_or(serde_json::Value::Object(serde_json::Map::new()));
-
-                let zhi_request: ZhiRequest = serde_json::from_value(arguments_value)
-                    .map_err(|e| McpError::invalid_params(format!("參數解析失敗: {}", e), None))?;
+                let zhi_request: ZhiRequest = parse_args(request.arguments)?;
 
                 // 呼叫寸止工具
-                InteractionTool::zhi(zhi_request).await
+                InteractionTool::zhi(zhi_request, context, self.config.current()).await
+            }
+            "zhi_form" => {
+                self.ensure_tool_enabled(TOOL_ZHI_FORM)?;
+                let form_request: ZhiFormRequest = parse_args(request.arguments)?;
+                InteractionTool::zhi_form(form_request, context, self.config.current()).await
+            }
+            "zhi_ask" => {
+                self.ensure_tool_enabled(TOOL_ZHI_ASK)?;
+                let zhi_request: ZhiRequest = parse_args(request.arguments)?;
+                InteractionTool::zhi_ask(zhi_request, context, self.config.current()).await
+            }
+            "zhi_poll" => {
+                self.ensure_tool_enabled(TOOL_ZHI_ASK)?;
+                let poll_request: ZhiPollRequest = parse_args(request.arguments)?;
+                InteractionTool::zhi_poll(poll_request, self.config.current()).await
+            }
+            "zhi_wait" => {
+                self.ensure_tool_enabled(TOOL_ZHI_ASK)?;
+                let wait_request: ZhiWaitRequest = parse_args(request.arguments)?;
+                InteractionTool::zhi_wait(wait_request, context, self.config.current()).await
+            }
+            "zhi_notify" => {
+                self.ensure_tool_enabled(TOOL_ZHI_NOTIFY)?;
+                let notify_request: ZhiNotifyRequest = parse_args(request.arguments)?;
+                InteractionTool::zhi_notify(notify_request, &self.session_id, &context.peer).await
             }
             "ji" => {
-                // 檢查記憶管理工具是否啟用
-                if !self.is_tool_enabled("ji") {
-                    return Err(McpError::internal_error(
-                        "記憶管理工具已被禁用".to_string(),
-                        None
-                    ));
-                }
-
-                // 解析請求參數
-                let arguments_value = request.arguments
-                    .map(serde_json::Value::Object)
-                    .unwrap_or(serde_json::Value::Object(serde_json::Map::new()));
-
-                let ji_request: JiyiRequest = serde_json::from_value(arguments_value)
-                    .map_err(|e| McpError::invalid_params(format!("參數解析失敗: {}", e), None))?;
-
-                // 呼叫記憶工具
-                MemoryTool::jiyi(ji_request).await
+                self.ensure_tool_enabled(TOOL_JI)?;
+                let ji_request: JiyiRequest = parse_args(request.arguments)?;
+
+                // 呼叫記憶工具，成功后將專案加入記憶資源列表
+                let project_path = ji_request.project_path.clone();
+                let result = MemoryTool::jiyi(ji_request, self.config.current()).await?;
+                self.memory_resources.register_project(&project_path);
+                Ok(result)
             }
             _ => {
                 Err(McpError::invalid_request(
@@ -219,9 +348,31 @@ impl ServerHandler for ZhiServer {
 }
 
 
+/// 將工具參數解析為請求類型，未提供參數時視為空物件
+fn parse_args<T: DeserializeOwned>(arguments: Option<serde_json::Map<String, serde_json::Value>>) -> Result<T, McpError> {
+    serde_json::from_value(serde_json::Value::Object(arguments.unwrap_or_default()))
+        .map_err(|e| McpError::invalid_params(format!("參數解析失敗: {}", e), None))
+}
 
 /// 啟動MCP服务器
+///
+/// 依命令列參數選擇 stdio 或 Streamable HTTP 傳輸
 pub async fn run_server() -> Result<(), Box<dyn std::error::Error>> {
+    let options = TransportOptions::from_args(std::env::args().skip(1))?;
+
+    match options.kind {
+        TransportKind::Stdio => run_stdio_server().await,
+        TransportKind::Http => {
+            serve_http(&options).await.inspect_err(|e| {
+                log_important!(error, "啟動 HTTP 服务器失敗: {}", e);
+            })?;
+            Ok(())
+        }
+    }
+}
+
+/// 以 stdio 傳輸執行單一客戶端的服务器
+async fn run_stdio_server() -> Result<(), Box<dyn std::error::Error>> {
     // 建立并執行服务器
     let service = ZhiServer::new()
         .serve(stdio())
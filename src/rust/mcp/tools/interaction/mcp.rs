@@ -1,9 +1,32 @@
 use anyhow::Result;
-use rmcp::{ErrorData as McpError, model::*};
+use rmcp::{ErrorData as McpError, model::*, service::RequestContext, Peer, RoleServer};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
 
-use crate::mcp::{ZhiRequest, PopupRequest};
-use crate::mcp::handlers::{create_tauri_popup, parse_mcp_response};
-use crate::mcp::utils::{generate_request_id, popup_error};
+use crate::config::{AppConfig, McpConfig};
+use crate::constants::mcp::{
+    DEFAULT_TICKET_WAIT_SECS, DISCONNECT_CHECK_INTERVAL_MS, INTERACTION_MODE_ELICITATION,
+    INTERACTION_MODE_ELICITATION_FALLBACK, INTERACTION_MODE_TERMINAL, TICKET_HEARTBEAT_SECS, TICKET_MAX_ATTEMPTS,
+    TICKET_POLL_INTERVAL_MS, TICKET_RETRY_DELAY_SECS,
+};
+use crate::mcp::{
+    DiffFile, FilePatchInput, PopupQuestion, PopupRequest, PredefinedOption, PredefinedOptionInput, QuestionInput,
+    Notification, SelectionMode, ZhiFormRequest, ZhiNotifyRequest, ZhiPollRequest, ZhiRequest, ZhiWaitRequest,
+};
+use crate::mcp::handlers::{
+    build_cancelled_result, build_form_result, build_form_timeout_result, build_timeout_result, build_tool_result,
+    create_elicitation, create_tauri_popup, create_terminal_prompt, gui_available, notify_daemon, PopupOutcome,
+};
+use crate::mcp::utils::{
+    generate_request_id, parse_form_schema, parse_unified_diff, popup_error, resolve_attachments,
+    resolve_request_origin,
+};
+use crate::{log_debug, log_important};
+
+use super::notification::NotificationLog;
+use super::ticket::{claim_ticket, release_ticket, Ticket, TicketStatus, TicketStore};
 
 /// 智能代码审查交互工具
 ///
@@ -14,27 +37,658 @@ pub struct InteractionTool;
 impl InteractionTool {
     pub async fn zhi(
         request: ZhiRequest,
+        context: RequestContext<RoleServer>,
+        config: Arc<AppConfig>,
     ) -> Result<CallToolResult, McpError> {
+        let popup_request = build_popup_request(request, &context.peer, &config).await?;
+
+        match run_interaction(&popup_request, &context, &config).await {
+            Ok(PopupOutcome::Response(response)) => {
+                // 解析回應內容，支持文本、图片和结构化輸出
+                build_tool_result(&response)
+            }
+            Ok(PopupOutcome::TimedOut) => timeout_result(&popup_request, &config),
+            Ok(PopupOutcome::Cancelled) => {
+                let reason = if context.peer.is_transport_closed() {
+                    "客戶端已斷線"
+                } else {
+                    "客戶端取消了請求"
+                };
+                build_cancelled_result(&popup_request.id, reason)
+            }
+            Err(e) => {
+                Err(popup_error(e.to_string()).into())
+            }
+        }
+    }
+
+    /// 以表單收集結構化的值
+    ///
+    /// 依 JSON Schema 渲染表單，送出的值經過驗證後以 `structuredContent` 傳回
+    pub async fn zhi_form(
+        request: ZhiFormRequest,
+        context: RequestContext<RoleServer>,
+        config: Arc<AppConfig>,
+    ) -> Result<CallToolResult, McpError> {
+        let form_fields = parse_form_schema(&request.schema)
+            .map_err(|e| McpError::invalid_params(format!("schema 無效: {}", e), None))?;
+
+        let timeout_seconds = request
+            .timeout_seconds
+            .unwrap_or(config.mcp_config.zhi_timeout_secs);
+
+        let origin = resolve_request_origin(&context.peer).await;
         let popup_request = PopupRequest {
             id: generate_request_id(),
             message: request.message,
-            predefined_options: if request.predefined_options.is_empty() {
-                None
-            } else {
-                Some(request.predefined_options)
-            },
+            predefined_options: None,
+            selection_mode: SelectionMode::default(),
             is_markdown: request.is_markdown,
+            timeout_seconds: (timeout_seconds > 0).then_some(timeout_seconds),
+            default_option: None,
+            client_name: origin.client_name,
+            client_version: origin.client_version,
+            project_name: origin.project_name,
+            project_path: origin.project_path,
+            git_branch: origin.git_branch,
+            questions: vec![],
+            diff_files: vec![],
+            form_fields,
+            attachments: vec![],
         };
 
-        match create_tauri_popup(&popup_request) {
-            Ok(response) => {
-                // 解析回應內容，支持文本和图片
-                let content = parse_mcp_response(&response)?;
-                Ok(CallToolResult::success(content))
+        match run_interaction(&popup_request, &context, &config).await {
+            Ok(PopupOutcome::Response(response)) => build_form_result(&response, &popup_request.form_fields),
+            Ok(PopupOutcome::TimedOut) => build_form_timeout_result(&popup_request.form_fields),
+            Ok(PopupOutcome::Cancelled) => {
+                let reason = if context.peer.is_transport_closed() {
+                    "客戶端已斷線"
+                } else {
+                    "客戶端取消了請求"
+                };
+                Ok(CallToolResult::error(vec![Content::text(format!("請求已取消：{}", reason))]))
             }
-            Err(e) => {
-                Err(popup_error(e.to_string()).into())
+            Err(e) => Err(popup_error(e.to_string()).into()),
+        }
+    }
+
+    /// 非阻塞詢問
+    ///
+    /// 參數与 `zhi` 相同，建立票證並在背景顯示弹窗後立即傳回票證 ID，
+    /// 之後以 `zhi_poll` 查詢或 `zhi_wait` 等待回覆
+    pub async fn zhi_ask(
+        request: ZhiRequest,
+        context: RequestContext<RoleServer>,
+        config: Arc<AppConfig>,
+    ) -> Result<CallToolResult, McpError> {
+        let popup_request = build_popup_request(request, &context.peer, &config).await?;
+        let ticket = Ticket::new(popup_request);
+        open_ticket_store()?
+            .save(&ticket)
+            .map_err(|e| McpError::internal_error(format!("儲存票證失敗: {}", e), None))?;
+        log_important!(info, "已建立非阻塞詢問票證: {}", ticket.id());
+
+        let ticket_id = ticket.id().to_string();
+        spawn_ticket(ticket, config);
+        ticket_status_result(
+            &ticket_id,
+            TicketStatus::Pending,
+            format!(
+                "已建立詢問，票證 ID：{}\n使用者回覆前可繼續其他工作，之後以 zhi_poll 查詢或 zhi_wait 等待回覆",
+                ticket_id
+            ),
+        )
+    }
+
+    /// 查詢票證，已回覆時傳回与 `zhi` 相同的結果
+    pub async fn zhi_poll(request: ZhiPollRequest, config: Arc<AppConfig>) -> Result<CallToolResult, McpError> {
+        let ticket = load_ticket(&open_ticket_store()?, &request.ticket_id)?;
+        resume_if_orphaned(&ticket, &config);
+        ticket_result(&ticket, &config)
+    }
+
+    /// 等待票證回覆，超過等待時間仍未回覆時傳回 pending 狀態
+    pub async fn zhi_wait(
+        request: ZhiWaitRequest,
+        context: RequestContext<RoleServer>,
+        config: Arc<AppConfig>,
+    ) -> Result<CallToolResult, McpError> {
+        let store = open_ticket_store()?;
+        let wait_secs = request.timeout_seconds.unwrap_or(DEFAULT_TICKET_WAIT_SECS);
+        let deadline = (wait_secs > 0).then(|| Instant::now() + Duration::from_secs(wait_secs));
+
+        let interval_secs = config.mcp_config.progress_interval_secs;
+        let heartbeat = context
+            .meta
+            .get_progress_token()
+            .filter(|_| interval_secs > 0)
+            .map(|token| tokio::spawn(send_progress_heartbeats(context.peer.clone(), token, interval_secs)));
+
+        let ticket = loop {
+            let ticket = load_ticket(&store, &request.ticket_id)?;
+            if ticket.status != TicketStatus::Pending || deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                break ticket;
+            }
+            resume_if_orphaned(&ticket, &config);
+
+            tokio::select! {
+                _ = tokio::time::sleep(Duration::from_millis(TICKET_POLL_INTERVAL_MS)) => {}
+                // 客戶端取消等待不影響票證，弹窗仍保留
+                _ = context.ct.cancelled() => break ticket,
+            }
+        };
+        if let Some(heartbeat) = heartbeat {
+            heartbeat.abort();
+        }
+        ticket_result(&ticket, &config)
+    }
+
+    /// 發出進度通知後立即傳回，不等待使用者
+    ///
+    /// 通知記錄於工作階段日誌；常駐的等一下執行中時另外更新系統匣並顯示提示
+    pub async fn zhi_notify(
+        request: ZhiNotifyRequest,
+        session_id: &str,
+        peer: &Peer<RoleServer>,
+    ) -> Result<CallToolResult, McpError> {
+        if request.message.trim().is_empty() {
+            return Err(McpError::invalid_params("message 不能為空".to_string(), None));
+        }
+        match (request.step, request.total) {
+            (_, Some(0)) => return Err(McpError::invalid_params("total 必須大於 0".to_string(), None)),
+            (Some(step), Some(total)) if step > total => {
+                return Err(McpError::invalid_params(format!("step {} 不能大於 total {}", step, total), None));
+            }
+            _ => {}
+        }
+
+        let origin = resolve_request_origin(peer).await;
+        let notification = Notification {
+            id: generate_request_id(),
+            session_id: session_id.to_string(),
+            title: request.title.filter(|title| !title.trim().is_empty()),
+            message: request.message,
+            step: request.step,
+            total: request.total,
+            level: request.level,
+            play_sound: request.play_sound,
+            client_name: origin.client_name,
+            project_name: origin.project_name,
+            created_at: chrono::Utc::now(),
+        };
+        log_important!(info, "進度通知: {}", notification.summary());
+
+        if let Err(e) = NotificationLog::open().and_then(|log| log.append(&notification)) {
+            log_important!(warn, "寫入通知日誌失敗: {}", e);
+        }
+
+        let message = if notify_daemon(&notification).await {
+            "通知已送出".to_string()
+        } else {
+            "通知已記錄於工作階段日誌（常駐的等一下未執行，未顯示提示）".to_string()
+        };
+        let mut result = CallToolResult::success(vec![Content::text(message)]);
+        result.structured_content = Some(serde_json::json!({ "notification_id": notification.id }));
+        Ok(result)
+    }
+}
+
+fn open_ticket_store() -> Result<TicketStore, McpError> {
+    TicketStore::open().map_err(|e| McpError::internal_error(format!("無法開啟票證目錄: {}", e), None))
+}
+
+fn load_ticket(store: &TicketStore, ticket_id: &str) -> Result<Ticket, McpError> {
+    match store.load(ticket_id) {
+        Ok(Some(ticket)) => Ok(ticket),
+        Ok(None) => Err(McpError::invalid_params(format!("找不到票證: {}（可能已過期）", ticket_id), None)),
+        Err(e) => Err(McpError::invalid_params(e.to_string(), None)),
+    }
+}
+
+/// 依票證狀態建立結果
+fn ticket_result(ticket: &Ticket, config: &AppConfig) -> Result<CallToolResult, McpError> {
+    match ticket.status {
+        TicketStatus::Pending => ticket_status_result(
+            ticket.id(),
+            ticket.status,
+            format!("使用者尚未回覆（票證 {}），可稍後再以 zhi_poll 查詢或 zhi_wait 等待", ticket.id()),
+        ),
+        TicketStatus::Answered => build_tool_result(ticket.response.as_deref().unwrap_or_default()),
+        TicketStatus::TimedOut => timeout_result(&ticket.request, config),
+        TicketStatus::Failed => Ok(CallToolResult::error(vec![Content::text(format!(
+            "票證 {} 的詢問失敗: {}",
+            ticket.id(),
+            ticket.error.as_deref().unwrap_or("未知錯誤")
+        ))])),
+    }
+}
+
+/// 尚未有結果的票證狀態，`structuredContent` 含票證 ID 与狀態
+fn ticket_status_result(ticket_id: &str, status: TicketStatus, message: String) -> Result<CallToolResult, McpError> {
+    let mut result = CallToolResult::success(vec![Content::text(message)]);
+    result.structured_content = Some(serde_json::json!({
+        "ticket_id": ticket_id,
+        "status": status.as_str(),
+    }));
+    Ok(result)
+}
+
+/// 負責的程序已停止時由目前程序接手，重新顯示弹窗
+fn resume_if_orphaned(ticket: &Ticket, config: &Arc<AppConfig>) {
+    if ticket.is_orphaned() {
+        log_important!(info, "票證 {} 無人處理，重新顯示弹窗", ticket.id());
+        spawn_ticket(ticket.clone(), config.clone());
+    }
+}
+
+/// 在背景等待票證回覆，同一票證在目前程序中只會處理一次
+fn spawn_ticket(ticket: Ticket, config: Arc<AppConfig>) {
+    if !claim_ticket(ticket.id()) {
+        return;
+    }
+    tokio::spawn(async move {
+        let ticket_id = ticket.id().to_string();
+        resolve_ticket(ticket, config).await;
+        release_ticket(&ticket_id);
+    });
+}
+
+/// 顯示票證的弹窗直到取得回覆或逾時，並將結果寫回票證
+///
+/// 弹窗中斷（例如等一下被關閉或重新啟動）時稍後重新顯示；
+/// 等待期間定期更新存活時間，讓其他程序知道票證仍有人處理
+async fn resolve_ticket(mut ticket: Ticket, config: Arc<AppConfig>) {
+    let store = match TicketStore::open() {
+        Ok(store) => store,
+        Err(e) => {
+            log_important!(error, "無法開啟票證目錄: {}", e);
+            return;
+        }
+    };
+    // 票證不隨客戶端請求取消，弹窗只會因回覆或逾時結束
+    let cancel = CancellationToken::new();
+    let mut attempts = 0;
+
+    loop {
+        // 重新顯示時以剩餘時間作為逾時
+        let mut request = ticket.request.clone();
+        request.timeout_seconds = ticket.remaining_timeout();
+        if request.timeout_seconds == Some(0) {
+            ticket.finish(TicketStatus::TimedOut, None, None);
+            break;
+        }
+
+        let ask = ask_detached(&request, &cancel, &config.mcp_config);
+        tokio::pin!(ask);
+        let mut heartbeat = tokio::time::interval(Duration::from_secs(TICKET_HEARTBEAT_SECS));
+        let outcome = loop {
+            tokio::select! {
+                outcome = &mut ask => break outcome,
+                _ = heartbeat.tick() => {
+                    ticket.heartbeat_at = Some(chrono::Utc::now());
+                    if let Err(e) = store.save(&ticket) {
+                        log_debug!("更新票證 {} 存活時間失敗: {}", ticket.id(), e);
+                    }
+                }
+            }
+        };
+
+        let error = match outcome {
+            Ok(PopupOutcome::Response(response)) => {
+                ticket.finish(TicketStatus::Answered, Some(response), None);
+                break;
+            }
+            Ok(PopupOutcome::TimedOut) => {
+                ticket.finish(TicketStatus::TimedOut, None, None);
+                break;
             }
+            Ok(PopupOutcome::Cancelled) => "弹窗已關閉".to_string(),
+            Err(e) => e.to_string(),
+        };
+
+        attempts += 1;
+        if attempts >= TICKET_MAX_ATTEMPTS {
+            ticket.finish(TicketStatus::Failed, None, Some(error));
+            break;
+        }
+        log_important!(
+            warn,
+            "票證 {} 的弹窗中斷（{}），{} 秒後重新顯示",
+            ticket.id(),
+            error,
+            TICKET_RETRY_DELAY_SECS
+        );
+        tokio::time::sleep(Duration::from_secs(TICKET_RETRY_DELAY_SECS)).await;
+    }
+
+    match store.save(&ticket) {
+        Ok(_) => log_important!(info, "票證 {} 已結束: {}", ticket.id(), ticket.status.as_str()),
+        Err(e) => log_important!(error, "儲存票證 {} 失敗: {}", ticket.id(), e),
+    }
+}
+
+/// 票證使用弹窗或終端詢問
+///
+/// elicitation 依附於發出請求的客戶端連線，無法在重新啟動後延續，因此不使用
+async fn ask_detached(
+    request: &PopupRequest,
+    cancel: &CancellationToken,
+    mcp_config: &McpConfig,
+) -> Result<PopupOutcome> {
+    match mcp_config.interaction_mode.as_str() {
+        INTERACTION_MODE_TERMINAL => create_terminal_prompt(request, cancel, &mcp_config.tmux_pane).await,
+        _ => popup_or_terminal(request, cancel, mcp_config).await,
+    }
+}
+
+/// 逾時時的結果，有預設選項時選擇該選項
+fn timeout_result(request: &PopupRequest, config: &AppConfig) -> Result<CallToolResult, McpError> {
+    let default_option = request.default_option.as_ref().and_then(|id| {
+        request
+            .predefined_options
+            .iter()
+            .flatten()
+            .find(|option| &option.id == id)
+    });
+    build_timeout_result(&request.id, default_option, &config.reply_config.continue_prompt)
+}
+
+/// 詢問使用者並等待回應
+///
+/// 等待期間監看客戶端取消与連線中斷，並在客戶端提供進度權杖時定期回報等待狀態
+async fn run_interaction(
+    request: &PopupRequest,
+    context: &RequestContext<RoleServer>,
+    config: &AppConfig,
+) -> Result<PopupOutcome> {
+    // 客戶端取消請求或連線中斷時關閉弹窗
+    let cancel = context.ct.child_token();
+    let watcher = tokio::spawn(cancel_on_disconnect(context.peer.clone(), cancel.clone()));
+
+    // 客戶端提供進度權杖時定期回報等待狀態，避免長時間審查觸發客戶端逾時
+    let interval_secs = config.mcp_config.progress_interval_secs;
+    let heartbeat = context
+        .meta
+        .get_progress_token()
+        .filter(|_| interval_secs > 0)
+        .map(|token| tokio::spawn(send_progress_heartbeats(context.peer.clone(), token, interval_secs)));
+
+    let outcome = ask_user(&context.peer, request, &cancel, &config.mcp_config).await;
+    watcher.abort();
+    if let Some(heartbeat) = heartbeat {
+        heartbeat.abort();
+    }
+    outcome
+}
+
+/// 由 `zhi` 請求建立弹窗請求
+async fn build_popup_request(
+    request: ZhiRequest,
+    peer: &Peer<RoleServer>,
+    config: &AppConfig,
+) -> Result<PopupRequest, McpError> {
+    let options = normalize_options(request.predefined_options)?;
+    let questions = normalize_questions(request.questions)?;
+    let diff_files = parse_review_diff(request.diff.as_deref(), request.patches)?;
+
+    // 預設選項可用 id 或文字指定，統一轉為 id
+    let default_option = match &request.default_option {
+        Some(value) => match options.iter().find(|option| &option.id == value || &option.label == value) {
+            Some(option) => Some(option.id.clone()),
+            None => {
+                return Err(McpError::invalid_params(
+                    format!("default_option 必須是 predefined_options 之一: {}", value),
+                    None,
+                ));
+            }
+        },
+        None => None,
+    };
+
+    // 請求未指定時使用全域預設逾時，0 表示不逾時
+    let timeout_seconds = request
+        .timeout_seconds
+        .unwrap_or(config.mcp_config.zhi_timeout_secs);
+
+    let origin = resolve_request_origin(peer).await;
+    // 附件只能讀取專案目錄与客戶端 roots 內的檔案，相對路徑以專案目錄為起點
+    let roots: Vec<PathBuf> = origin.project_path.iter().map(PathBuf::from).chain(origin.roots.iter().cloned()).collect();
+    let attachments = resolve_attachments(request.attachments, &roots)
+        .map_err(|e| McpError::invalid_params(e.to_string(), None))?;
+    Ok(PopupRequest {
+        id: generate_request_id(),
+        message: request.message,
+        predefined_options: if options.is_empty() { None } else { Some(options) },
+        selection_mode: request.selection_mode,
+        is_markdown: request.is_markdown,
+        timeout_seconds: (timeout_seconds > 0).then_some(timeout_seconds),
+        default_option,
+        client_name: origin.client_name,
+        client_version: origin.client_version,
+        project_name: origin.project_name,
+        project_path: origin.project_path,
+        git_branch: origin.git_branch,
+        questions,
+        diff_files,
+        form_fields: vec![],
+        attachments,
+    })
+}
+
+/// 正規化預定義選項並檢查 id 不重複
+fn normalize_options(inputs: Vec<PredefinedOptionInput>) -> Result<Vec<PredefinedOption>, McpError> {
+    let mut options: Vec<PredefinedOption> = Vec::with_capacity(inputs.len());
+    for option in inputs.into_iter().map(PredefinedOption::from) {
+        if option.label.trim().is_empty() {
+            return Err(McpError::invalid_params("predefined_options 的 label 不能為空".to_string(), None));
+        }
+        if options.iter().any(|existing| existing.id == option.id) {
+            return Err(McpError::invalid_params(
+                format!("predefined_options 的 id 重複: {}", option.id),
+                None,
+            ));
         }
+        options.push(option);
+    }
+    Ok(options)
+}
+
+/// 正規化問題，未指定 id 時依序命名為 q1、q2…
+fn normalize_questions(inputs: Vec<QuestionInput>) -> Result<Vec<PopupQuestion>, McpError> {
+    let mut questions: Vec<PopupQuestion> = Vec::with_capacity(inputs.len());
+    for (index, input) in inputs.into_iter().enumerate() {
+        if input.message.trim().is_empty() {
+            return Err(McpError::invalid_params(format!("questions 第 {} 題的 message 不能為空", index + 1), None));
+        }
+        let id = input
+            .id
+            .filter(|id| !id.trim().is_empty())
+            .unwrap_or_else(|| format!("q{}", index + 1));
+        if questions.iter().any(|question| question.id == id) {
+            return Err(McpError::invalid_params(format!("questions 的 id 重複: {}", id), None));
+        }
+        questions.push(PopupQuestion {
+            id,
+            message: input.message,
+            predefined_options: normalize_options(input.predefined_options)?,
+            selection_mode: input.selection_mode,
+        });
+    }
+    Ok(questions)
+}
+
+/// 解析要審查的 diff 与檔案 patch
+fn parse_review_diff(diff: Option<&str>, patches: Vec<FilePatchInput>) -> Result<Vec<DiffFile>, McpError> {
+    let mut files = Vec::new();
+    if let Some(diff) = diff.filter(|diff| !diff.trim().is_empty()) {
+        files.extend(
+            parse_unified_diff(diff, None)
+                .map_err(|e| McpError::invalid_params(format!("diff 解析失敗: {}", e), None))?,
+        );
+    }
+    for patch in patches {
+        files.extend(
+            parse_unified_diff(&patch.diff, Some(&patch.path))
+                .map_err(|e| McpError::invalid_params(format!("{} 的 patch 解析失敗: {}", patch.path, e), None))?,
+        );
+    }
+    Ok(files)
+}
+
+/// 依互動模式透過弹窗、終端或客戶端的 elicitation 詢問使用者
+async fn ask_user(
+    peer: &Peer<RoleServer>,
+    request: &PopupRequest,
+    cancel: &CancellationToken,
+    mcp_config: &McpConfig,
+) -> Result<PopupOutcome> {
+    let supports_elicitation = peer.supports_elicitation();
+
+    match mcp_config.interaction_mode.as_str() {
+        INTERACTION_MODE_TERMINAL => create_terminal_prompt(request, cancel, &mcp_config.tmux_pane).await,
+        INTERACTION_MODE_ELICITATION => {
+            if !supports_elicitation {
+                anyhow::bail!("客戶端不支援 elicitation，請將互動模式改為弹窗或 elicitation_fallback");
+            }
+            create_elicitation(peer, request, cancel).await
+        }
+        INTERACTION_MODE_ELICITATION_FALLBACK if supports_elicitation => {
+            match create_elicitation(peer, request, cancel).await {
+                Err(e) => {
+                    log_important!(warn, "elicitation 失敗，改用弹窗: {}", e);
+                    popup_or_terminal(request, cancel, mcp_config).await
+                }
+                outcome => outcome,
+            }
+        }
+        _ => popup_or_terminal(request, cancel, mcp_config).await,
+    }
+}
+
+/// 開啟弹窗；沒有圖形介面或弹窗無法啟動時改用終端
+async fn popup_or_terminal(
+    request: &PopupRequest,
+    cancel: &CancellationToken,
+    mcp_config: &McpConfig,
+) -> Result<PopupOutcome> {
+    if !mcp_config.terminal_fallback {
+        return create_tauri_popup(request, cancel).await;
+    }
+
+    if !gui_available() {
+        log_important!(info, "未偵測到圖形介面，改用終端詢問");
+        return create_terminal_prompt(request, cancel, &mcp_config.tmux_pane).await;
+    }
+
+    match create_tauri_popup(request, cancel).await {
+        Err(e) => {
+            log_important!(warn, "弹窗失敗，改用終端詢問: {}", e);
+            create_terminal_prompt(request, cancel, &mcp_config.tmux_pane)
+                .await
+                .map_err(|terminal_error| anyhow::anyhow!("{}；終端後備也失敗: {}", e, terminal_error))
+        }
+        outcome => outcome,
+    }
+}
+
+/// 定期檢查客戶端連線，連線關閉（例如 stdin 已到 EOF）時觸發取消
+async fn cancel_on_disconnect(peer: Peer<RoleServer>, cancel: CancellationToken) {
+    let mut interval = tokio::time::interval(Duration::from_millis(DISCONNECT_CHECK_INTERVAL_MS));
+    loop {
+        tokio::select! {
+            _ = cancel.cancelled() => break,
+            _ = interval.tick() => {
+                if peer.is_transport_closed() {
+                    cancel.cancel();
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// 弹窗開啟期間定期傳送 `notifications/progress`，進度值為已等待的秒數
+async fn send_progress_heartbeats(peer: Peer<RoleServer>, progress_token: ProgressToken, interval_secs: u64) {
+    let started = Instant::now();
+    let mut interval = tokio::time::interval(Duration::from_secs(interval_secs));
+    // 第一次 tick 會立即完成，跳過以免剛開啟弹窗就回報
+    interval.tick().await;
+
+    loop {
+        interval.tick().await;
+        let elapsed = started.elapsed().as_secs();
+        let result = peer
+            .notify_progress(ProgressNotificationParam {
+                progress_token: progress_token.clone(),
+                progress: elapsed as f64,
+                total: None,
+                message: Some(format!("等待使用者回應中，已等待 {}", format_elapsed(elapsed))),
+            })
+            .await;
+        if let Err(e) = result {
+            log_debug!("傳送進度通知失敗，停止回報: {}", e);
+            break;
+        }
+    }
+}
+
+/// 將秒數格式化為「X 分 Y 秒」
+fn format_elapsed(secs: u64) -> String {
+    if secs < 60 {
+        format!("{} 秒", secs)
+    } else {
+        format!("{} 分 {} 秒", secs / 60, secs % 60)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn option_inputs(value: serde_json::Value) -> Vec<PredefinedOptionInput> {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_normalize_options() {
+        // 純文字選項以 label 為 id，空白的 id 也退回 label
+        let options = normalize_options(option_inputs(json!([
+            "繼續",
+            { "id": " ", "label": "停止", "exclusive": true },
+            { "id": "later", "label": "稍後" },
+        ])))
+        .unwrap();
+        let ids: Vec<&str> = options.iter().map(|option| option.id.as_str()).collect();
+        assert_eq!(ids, vec!["繼續", "停止", "later"]);
+        assert!(options[1].exclusive);
+
+        // 重複的 id（包含退回 label 後重複）与空白的 label
+        assert!(normalize_options(option_inputs(json!(["A", { "id": "A", "label": "另一個" }]))).is_err());
+        assert!(normalize_options(option_inputs(json!(["A", { "id": "", "label": "A" }]))).is_err());
+        assert!(normalize_options(option_inputs(json!([{ "label": "  " }]))).is_err());
+    }
+
+    #[test]
+    fn test_normalize_questions() {
+        let questions: Vec<QuestionInput> = serde_json::from_value(json!([
+            { "message": "使用哪種語言？", "predefined_options": ["Rust", "Go"], "selection_mode": "single" },
+            { "id": "db", "message": "資料庫？" },
+            { "id": "", "message": "其他需求？" },
+        ]))
+        .unwrap();
+        let questions = normalize_questions(questions).unwrap();
+        let ids: Vec<&str> = questions.iter().map(|question| question.id.as_str()).collect();
+        assert_eq!(ids, vec!["q1", "db", "q3"]);
+        assert_eq!(questions[0].selection_mode, SelectionMode::Single);
+        assert_eq!(questions[0].predefined_options.len(), 2);
+        assert_eq!(questions[1].selection_mode, SelectionMode::Multiple);
+
+        let normalize = |value| normalize_questions(serde_json::from_value(value).unwrap());
+        // 指定的 id 与預設的 q2 重複
+        assert!(normalize(json!([{ "id": "q2", "message": "一" }, { "message": "二" }])).is_err());
+        assert!(normalize(json!([{ "message": " " }])).is_err());
+        // 問題選項的錯誤同樣傳回
+        assert!(normalize(json!([{ "message": "一", "predefined_options": ["A", "A"] }])).is_err());
     }
 }
@@ -3,6 +3,8 @@
 //! 提供智能代码审查交互功能，支持预定義選項、自由文本輸入和图片上传
 
 pub mod mcp;
+pub mod notification;
+pub mod ticket;
 
 // 重新匯出主要類型和功能
 pub use mcp::InteractionTool;
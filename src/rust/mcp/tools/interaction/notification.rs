@@ -0,0 +1,67 @@
+//! 進度通知的工作階段日誌
+//!
+//! `zhi_notify` 的通知依 MCP 工作階段保存在設定目錄的 `notifications` 下，
+//! 常駐等一下是否執行都能在主界面查看最近的狀態更新
+
+use anyhow::Result;
+use std::sync::Mutex;
+
+use crate::constants::mcp::{NOTIFICATION_DIR_NAME, NOTIFICATION_LOG_LIMIT, NOTIFICATION_RETENTION_SECS};
+use crate::log_debug;
+use crate::mcp::types::{Notification, NotificationSession};
+use crate::mcp::utils::JsonDir;
+
+/// 同一程序內的寫入依序進行，避免同時讀改寫同一個日誌
+static LOG_WRITE_LOCK: Mutex<()> = Mutex::new(());
+
+/// 通知日誌，每個工作階段一個檔案
+pub struct NotificationLog {
+    files: JsonDir,
+}
+
+impl NotificationLog {
+    /// 開啟設定目錄下的通知目錄，並清理過期的日誌
+    pub fn open() -> Result<Self> {
+        let files = JsonDir::open(NOTIFICATION_DIR_NAME)?;
+        files.cleanup_expired(NOTIFICATION_RETENTION_SECS);
+        Ok(Self { files })
+    }
+
+    /// 附加一則通知，只保留最近的 `NOTIFICATION_LOG_LIMIT` 則
+    pub fn append(&self, notification: &Notification) -> Result<()> {
+        let _guard = LOG_WRITE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+        let existing = self.files.load(&notification.session_id).unwrap_or_else(|e| {
+            log_debug!("讀取通知日誌失敗，重新建立: {}", e);
+            None
+        });
+        let mut session = existing.unwrap_or_else(|| NotificationSession {
+            session_id: notification.session_id.clone(),
+            client_name: None,
+            project_name: None,
+            updated_at: notification.created_at,
+            notifications: Vec::new(),
+        });
+        session.client_name = notification.client_name.clone().or(session.client_name);
+        session.project_name = notification.project_name.clone().or(session.project_name);
+        session.updated_at = notification.created_at;
+        session.notifications.push(notification.clone());
+        let overflow = session.notifications.len().saturating_sub(NOTIFICATION_LOG_LIMIT);
+        session.notifications.drain(..overflow);
+
+        self.files.save(&notification.session_id, &session)
+    }
+
+    /// 所有工作階段的日誌，最近更新的在前
+    pub fn sessions(&self) -> Result<Vec<NotificationSession>> {
+        let mut sessions: Vec<NotificationSession> = self.files.load_all()?;
+        sessions.sort_by_key(|session| std::cmp::Reverse(session.updated_at));
+        Ok(sessions)
+    }
+
+    /// 清除所有工作階段的日誌
+    pub fn clear(&self) -> Result<()> {
+        let _guard = LOG_WRITE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        self.files.clear()
+    }
+}
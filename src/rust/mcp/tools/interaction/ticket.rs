@@ -0,0 +1,212 @@
+//! 非阻塞詢問的票證
+//!
+//! `zhi_ask` 建立的詢問以 JSON 保存在設定目錄的 `tickets` 下，
+//! 等一下或 MCP 服务器重新啟動後仍能重新顯示弹窗並取得回覆
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::{Mutex, Once};
+
+use crate::constants::mcp::{TICKET_DIR_NAME, TICKET_RETENTION_SECS, TICKET_STALE_SECS};
+use crate::mcp::types::PopupRequest;
+use crate::mcp::utils::JsonDir;
+
+/// 目前程序中正在等待回覆的票證
+static RUNNING_TICKETS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// 每個程序只清理一次過期票證，`zhi_poll` 頻繁開啟票證目錄時不必重複掃描
+static CLEANUP_ONCE: Once = Once::new();
+
+/// 票證狀態
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TicketStatus {
+    /// 等待使用者回覆
+    Pending,
+    /// 已取得回覆
+    Answered,
+    /// 超過逾時仍未回覆
+    TimedOut,
+    /// 弹窗多次啟動失敗
+    Failed,
+}
+
+impl TicketStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Answered => "answered",
+            Self::TimedOut => "timed_out",
+            Self::Failed => "failed",
+        }
+    }
+}
+
+/// 一次非阻塞詢問，票證 ID 即弹窗請求 ID
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Ticket {
+    pub request: PopupRequest,
+    pub status: TicketStatus,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    /// 負責顯示弹窗的程序最後一次回報存活的時間
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub heartbeat_at: Option<DateTime<Utc>>,
+    /// UI 傳回的原始回應
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response: Option<String>,
+    /// 失敗原因
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl Ticket {
+    pub fn new(request: PopupRequest) -> Self {
+        let now = Utc::now();
+        Self {
+            request,
+            status: TicketStatus::Pending,
+            created_at: now,
+            updated_at: now,
+            heartbeat_at: None,
+            response: None,
+            error: None,
+        }
+    }
+
+    pub fn id(&self) -> &str {
+        &self.request.id
+    }
+
+    /// 從建立時間起算的剩餘逾時秒數，未設定逾時時傳回 None
+    pub fn remaining_timeout(&self) -> Option<u64> {
+        let elapsed = Utc::now().signed_duration_since(self.created_at).num_seconds().max(0) as u64;
+        self.request.timeout_seconds.map(|secs| secs.saturating_sub(elapsed))
+    }
+
+    /// 仍在等待，但負責的程序已停止回報（例如 MCP 服务器已重新啟動）
+    pub fn is_orphaned(&self) -> bool {
+        let last_seen = self.heartbeat_at.unwrap_or(self.created_at);
+        self.status == TicketStatus::Pending
+            && Utc::now().signed_duration_since(last_seen).num_seconds() > TICKET_STALE_SECS as i64
+    }
+
+    /// 更新狀態与時間
+    pub fn finish(&mut self, status: TicketStatus, response: Option<String>, error: Option<String>) {
+        self.status = status;
+        self.response = response;
+        self.error = error;
+        self.updated_at = Utc::now();
+    }
+}
+
+/// 標記票證由目前程序處理，已在處理中時傳回 false
+pub fn claim_ticket(id: &str) -> bool {
+    let mut running = RUNNING_TICKETS.lock().unwrap_or_else(|e| e.into_inner());
+    if running.iter().any(|running_id| running_id == id) {
+        return false;
+    }
+    running.push(id.to_string());
+    true
+}
+
+/// 票證處理結束
+pub fn release_ticket(id: &str) {
+    let mut running = RUNNING_TICKETS.lock().unwrap_or_else(|e| e.into_inner());
+    running.retain(|running_id| running_id != id);
+}
+
+/// 票證儲存區，每張票證一個檔案
+pub struct TicketStore {
+    files: JsonDir,
+}
+
+impl TicketStore {
+    /// 開啟設定目錄下的票證目錄，程序內第一次開啟時清理過期的票證
+    pub fn open() -> Result<Self> {
+        let files = JsonDir::open(TICKET_DIR_NAME)?;
+        CLEANUP_ONCE.call_once(|| files.cleanup_expired(TICKET_RETENTION_SECS));
+        Ok(Self { files })
+    }
+
+    /// 寫入票證
+    pub fn save(&self, ticket: &Ticket) -> Result<()> {
+        self.files.save(ticket.id(), ticket)
+    }
+
+    /// 讀取票證，不存在時傳回 None
+    pub fn load(&self, id: &str) -> Result<Option<Ticket>> {
+        self.files.load(id).map_err(|e| anyhow::anyhow!("讀取票證失敗: {}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn ticket(timeout_seconds: Option<u64>) -> Ticket {
+        let request: PopupRequest = serde_json::from_value(serde_json::json!({
+            "id": "ticket-1",
+            "message": "繼續嗎？",
+            "predefined_options": null,
+            "is_markdown": true,
+            "timeout_seconds": timeout_seconds,
+        }))
+        .unwrap();
+        Ticket::new(request)
+    }
+
+    #[test]
+    fn test_ticket_orphaned() {
+        let mut ticket = ticket(None);
+        assert!(!ticket.is_orphaned());
+
+        // 建立後超過時限都沒有回報存活
+        ticket.created_at -= Duration::seconds(TICKET_STALE_SECS as i64 + 1);
+        assert!(ticket.is_orphaned());
+
+        // 有近期的存活回報
+        ticket.heartbeat_at = Some(Utc::now());
+        assert!(!ticket.is_orphaned());
+
+        // 已結束的票證不需要接手
+        ticket.heartbeat_at = None;
+        ticket.finish(TicketStatus::Answered, Some("ok".to_string()), None);
+        assert!(!ticket.is_orphaned());
+    }
+
+    #[test]
+    fn test_ticket_remaining_timeout() {
+        assert_eq!(ticket(None).remaining_timeout(), None);
+
+        let mut ticket = ticket(Some(30));
+        ticket.created_at -= Duration::seconds(10);
+        assert!(matches!(ticket.remaining_timeout(), Some(19..=20)));
+
+        ticket.created_at -= Duration::seconds(60);
+        assert_eq!(ticket.remaining_timeout(), Some(0));
+    }
+
+    #[test]
+    fn test_ticket_store() {
+        let dir = std::env::temp_dir().join(format!("cunzhi-ticket-{}", std::process::id()));
+        let store = TicketStore { files: JsonDir::new(dir.clone()).unwrap() };
+
+        assert!(store.load("../x").is_err());
+        assert!(store.load("").is_err());
+        assert!(store.load("missing").unwrap().is_none());
+
+        let mut saved = ticket(Some(30));
+        saved.finish(TicketStatus::Answered, Some("回覆".to_string()), None);
+        store.save(&saved).unwrap();
+        let loaded = store.load(saved.id()).unwrap().unwrap();
+        assert_eq!(loaded.status, TicketStatus::Answered);
+        assert_eq!(loaded.response.as_deref(), Some("回覆"));
+        assert_eq!(loaded.request.timeout_seconds, Some(30));
+        assert_eq!(loaded.created_at, saved.created_at);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,9 +1,27 @@
 use anyhow::Result;
-use chrono::Utc;
+use chrono::{DateTime, SecondsFormat, Utc};
+use std::collections::HashSet;
 use std::fs;
 use std::path::{Path, PathBuf};
 
-use super::types::{MemoryEntry, MemoryCategory, MemoryMetadata};
+use super::search::similarity;
+use super::types::{AddMemoryOutcome, MemoryEntry, MemoryCategory, MemoryMetadata};
+use crate::config::get_standalone_config_path;
+use crate::constants::mcp::{GLOBAL_MEMORY_DIR_NAME, MEMORY_DIR_NAME, MEMORY_DUPLICATE_SIMILARITY};
+use crate::log_important;
+
+/// 記憶條目行尾的 HTML 註解，保存 ID 与時間，Markdown 顯示時不可見
+///
+/// 格式：`- 內容 <!-- cunzhi:id=...;created=...;updated=... -->`
+const ENTRY_META_PREFIX: &str = "<!-- cunzhi:";
+const ENTRY_META_SUFFIX: &str = "-->";
+
+/// 條目行尾註解中的 ID 与時間
+struct EntryMeta {
+    id: String,
+    created_at: Option<DateTime<Utc>>,
+    updated_at: Option<DateTime<Utc>>,
+}
 
 /// 記憶管理器
 pub struct MemoryManager {
@@ -16,7 +34,7 @@ impl MemoryManager {
     pub fn new(project_path: &str) -> Result<Self> {
         // 规范化專案路径
         let normalized_path = Self::normalize_project_path(project_path)?;
-        let memory_dir = normalized_path.join(".cunzhi-memory");
+        let memory_dir = normalized_path.join(MEMORY_DIR_NAME);
 
         // 建立記憶目录，如果失敗则说明專案不适合使用記憶功能
         fs::create_dir_all(&memory_dir)
@@ -26,10 +44,21 @@ impl MemoryManager {
                 e
             ))?;
 
-        let manager = Self {
-            memory_dir,
-            project_path: normalized_path.to_string_lossy().to_string(),
-        };
+        Self::open(memory_dir, normalized_path.to_string_lossy().to_string())
+    }
+
+    /// 建立使用者層級的全域記憶管理器，記憶存放於設定目錄下，所有專案共用
+    pub fn global() -> Result<Self> {
+        let memory_dir = get_standalone_config_path()?.with_file_name(GLOBAL_MEMORY_DIR_NAME);
+        fs::create_dir_all(&memory_dir)
+            .map_err(|e| anyhow::anyhow!("無法建立全域記憶目錄: {}\n錯誤: {}", memory_dir.display(), e))?;
+
+        let project_path = memory_dir.to_string_lossy().to_string();
+        Self::open(memory_dir, project_path)
+    }
+
+    fn open(memory_dir: PathBuf, project_path: String) -> Result<Self> {
+        let manager = Self { memory_dir, project_path };
 
         // 初始化記憶檔案结构
         manager.initialize_memory_structure()?;
@@ -37,6 +66,11 @@ impl MemoryManager {
         Ok(manager)
     }
 
+    /// 解析專案路径对应的 git 根目录（不建立記憶目录）
+    pub fn resolve_project_root(project_path: &str) -> Result<PathBuf> {
+        Self::normalize_project_path(project_path)
+    }
+
     /// 规范化專案路径
     fn normalize_project_path(project_path: &str) -> Result<PathBuf> {
         // 使用增强的路径解码和规范化功能
@@ -140,22 +174,8 @@ impl MemoryManager {
     /// 初始化記憶檔案结构
     fn initialize_memory_structure(&self) -> Result<()> {
         // 建立各類記憶檔案，使用新的结构化格式
-        let categories = [
-            MemoryCategory::Rule,
-            MemoryCategory::Preference,
-            MemoryCategory::Pattern,
-            MemoryCategory::Context,
-        ];
-
-        for category in categories.iter() {
-            let filename = match category {
-                MemoryCategory::Rule => "rules.md",
-                MemoryCategory::Preference => "preferences.md",
-                MemoryCategory::Pattern => "patterns.md",
-                MemoryCategory::Context => "context.md",
-            };
-
-            let file_path = self.memory_dir.join(filename);
+        for category in MemoryCategory::ALL.iter() {
+            let file_path = self.memory_dir.join(category.file_name());
             if !file_path.exists() {
                 let header_content = self.get_category_header(category);
                 fs::write(&file_path, header_content)?;
@@ -169,13 +189,31 @@ impl MemoryManager {
     }
 
     /// 新增記憶条目
-    pub fn add_memory(&self, content: &str, category: MemoryCategory) -> Result<String> {
-        let id = uuid::Uuid::new_v4().to_string();
-        let now = Utc::now();
+    ///
+    /// 同分類已有相似度達 `MEMORY_DUPLICATE_SIMILARITY` 的記憶時不新增：
+    /// `auto_merge` 為 true 時以新內容取代該記憶，否則傳回該記憶供呼叫端提示修改
+    pub fn add_memory(&self, content: &str, category: MemoryCategory, auto_merge: bool) -> Result<AddMemoryOutcome> {
+        let content = single_line(content);
+
+        let closest = self
+            .load_category(category)?
+            .into_iter()
+            .map(|entry| (similarity(&entry.content, &content), entry))
+            .max_by(|(a, _), (b, _)| a.total_cmp(b));
+        if let Some((score, existing)) = closest.filter(|(score, _)| *score >= MEMORY_DUPLICATE_SIMILARITY) {
+            if !auto_merge {
+                return Ok(AddMemoryOutcome::Duplicate { existing, similarity: score });
+            }
+            let merged = self
+                .update_memory(&existing.id, &content)?
+                .ok_or_else(|| anyhow::anyhow!("記憶 {} 已不存在", existing.id))?;
+            return Ok(AddMemoryOutcome::Merged { previous: existing, merged });
+        }
 
+        let now = Utc::now();
         let entry = MemoryEntry {
-            id: id.clone(),
-            content: content.to_string(),
+            id: uuid::Uuid::new_v4().to_string(),
+            content,
             category,
             created_at: now,
             updated_at: now,
@@ -187,27 +225,15 @@ impl MemoryManager {
         // 更新元資料
         self.update_metadata()?;
 
-        Ok(id)
+        Ok(AddMemoryOutcome::Added(entry))
     }
 
     /// 獲取所有記憶
     pub fn get_all_memories(&self) -> Result<Vec<MemoryEntry>> {
         let mut memories = Vec::new();
 
-        let categories = [
-            (MemoryCategory::Rule, "rules.md"),
-            (MemoryCategory::Preference, "preferences.md"),
-            (MemoryCategory::Pattern, "patterns.md"),
-            (MemoryCategory::Context, "context.md"),
-        ];
-
-        for (category, filename) in categories.iter() {
-            let file_path = self.memory_dir.join(filename);
-            if file_path.exists() {
-                let content = fs::read_to_string(&file_path)?;
-                let entries = self.parse_memory_file(&content, *category)?;
-                memories.extend(entries);
-            }
+        for category in MemoryCategory::ALL.iter() {
+            memories.extend(self.load_category(*category)?);
         }
 
         // 按更新時间排序
@@ -218,79 +244,167 @@ impl MemoryManager {
 
     /// 獲取指定分類的記憶
     pub fn get_memories_by_category(&self, category: MemoryCategory) -> Result<Vec<MemoryEntry>> {
-        let filename = match category {
-            MemoryCategory::Rule => "rules.md",
-            MemoryCategory::Preference => "preferences.md",
-            MemoryCategory::Pattern => "patterns.md",
-            MemoryCategory::Context => "context.md",
+        self.load_category(category)
+    }
+
+    /// 依 ID 獲取記憶
+    pub fn get_memory(&self, id: &str) -> Result<Option<MemoryEntry>> {
+        for category in MemoryCategory::ALL.iter() {
+            if let Some(entry) = self.load_category(*category)?.into_iter().find(|entry| entry.id == id) {
+                return Ok(Some(entry));
+            }
+        }
+        Ok(None)
+    }
+
+    /// 依 ID 修改記憶內容，傳回修改後的条目，找不到時傳回 None
+    pub fn update_memory(&self, id: &str, content: &str) -> Result<Option<MemoryEntry>> {
+        let Some(mut entry) = self.get_memory(id)? else {
+            return Ok(None);
         };
+        entry.content = single_line(content);
+        entry.updated_at = Utc::now();
 
-        let file_path = self.memory_dir.join(filename);
-        if !file_path.exists() {
-            return Ok(Vec::new());
+        self.replace_entry_line(entry.category, id, Some(&entry))?;
+        self.update_metadata()?;
+        Ok(Some(entry))
+    }
+
+    /// 依 ID 刪除記憶，傳回被刪除的条目，找不到時傳回 None
+    pub fn delete_memory(&self, id: &str) -> Result<Option<MemoryEntry>> {
+        let Some(entry) = self.get_memory(id)? else {
+            return Ok(None);
+        };
+
+        self.replace_entry_line(entry.category, id, None)?;
+        self.update_metadata()?;
+        Ok(Some(entry))
+    }
+
+    /// 依 ID 將記憶移到其他分類，保留 ID 与建立時間
+    pub fn move_memory(&self, id: &str, category: MemoryCategory) -> Result<Option<MemoryEntry>> {
+        let Some(mut entry) = self.get_memory(id)? else {
+            return Ok(None);
+        };
+        if entry.category == category {
+            return Ok(Some(entry));
         }
 
+        self.replace_entry_line(entry.category, id, None)?;
+        entry.category = category;
+        entry.updated_at = Utc::now();
+        self.append_to_category_file(&entry)?;
+        self.update_metadata()?;
+        Ok(Some(entry))
+    }
+
+    /// 取代或移除分類檔案中指定 ID 的条目行，其余內容保持原样
+    fn replace_entry_line(&self, category: MemoryCategory, id: &str, replacement: Option<&MemoryEntry>) -> Result<()> {
+        let file_path = self.memory_dir.join(category.file_name());
         let content = fs::read_to_string(&file_path)?;
-        self.parse_memory_file(&content, category)
+
+        let lines: Vec<String> = parse_entry_lines(&content)
+            .filter_map(|(line, parsed)| {
+                let matches = parsed
+                    .and_then(|(_, meta)| meta)
+                    .is_some_and(|meta| meta.id == id);
+                match (matches, replacement) {
+                    (false, _) => Some(line.to_string()),
+                    (true, Some(entry)) => Some(format_entry_line(entry)),
+                    (true, None) => None,
+                }
+            })
+            .collect();
+
+        write_category_file(&file_path, &lines)
     }
 
     /// 将記憶条目新增到对应分類檔案
     fn append_to_category_file(&self, entry: &MemoryEntry) -> Result<()> {
-        let filename = match entry.category {
-            MemoryCategory::Rule => "rules.md",
-            MemoryCategory::Preference => "preferences.md",
-            MemoryCategory::Pattern => "patterns.md",
-            MemoryCategory::Context => "context.md",
-        };
-
-        let file_path = self.memory_dir.join(filename);
+        let file_path = self.memory_dir.join(entry.category.file_name());
         let mut content = if file_path.exists() {
             fs::read_to_string(&file_path)?
         } else {
             format!("# {}\n\n", self.get_category_title(&entry.category))
         };
 
-        // 简化格式：一行一个記憶
-        content.push_str(&format!("- {}\n", entry.content));
+        // 一行一个記憶，ID 与時間保存在行尾的註解中
+        if !content.is_empty() && !content.ends_with('\n') {
+            content.push('\n');
+        }
+        content.push_str(&format_entry_line(entry));
+        content.push('\n');
 
         fs::write(&file_path, content)?;
         Ok(())
     }
 
-    /// 解析記憶檔案內容 - 简化版本
-    fn parse_memory_file(&self, content: &str, category: MemoryCategory) -> Result<Vec<MemoryEntry>> {
-        let mut memories = Vec::new();
+    /// 讀取分類檔案中的記憶
+    ///
+    /// 缺少 ID 与時間的条目（舊格式或手動新增的行）会補上後寫回檔案，時間以檔案修改時間为準；
+    /// 重複的 ID（例如複製貼上的行）会重新產生，其余內容保持原样。
+    /// 補上的 ID 必須寫回，之後才能依 ID 查看或修改，因此唯讀的查詢也可能改寫檔案，改寫時記錄在日誌中
+    fn load_category(&self, category: MemoryCategory) -> Result<Vec<MemoryEntry>> {
+        let file_path = self.memory_dir.join(category.file_name());
+        if !file_path.exists() {
+            return Ok(Vec::new());
+        }
 
-        // 按列表项解析，每个 "- " 开头的行是一个記憶条目
-        for line in content.lines() {
-            let line = line.trim();
-            if line.starts_with("- ") && line.len() > 2 {
-                let content = line[2..].trim(); // 去掉 "- " 前缀
-                if !content.is_empty() {
-                    let entry = MemoryEntry {
-                        id: uuid::Uuid::new_v4().to_string(),
-                        content: content.to_string(),
-                        category,
-                        created_at: Utc::now(),
-                        updated_at: Utc::now(),
-                    };
+        let content = fs::read_to_string(&file_path)?;
+        let file_time = fs::metadata(&file_path)
+            .and_then(|metadata| metadata.modified())
+            .map(DateTime::<Utc>::from)
+            .unwrap_or_else(|_| Utc::now());
+
+        let mut entries = Vec::new();
+        let mut lines = Vec::new();
+        let mut seen_ids = HashSet::new();
+        let mut changed = false;
+
+        for (line, parsed) in parse_entry_lines(&content) {
+            let Some((text, meta)) = parsed else {
+                lines.push(line.to_string());
+                continue;
+            };
 
-                    memories.push(entry);
+            let entry = match meta {
+                Some(meta) if seen_ids.insert(meta.id.clone()) => MemoryEntry {
+                    id: meta.id,
+                    content: text,
+                    category,
+                    created_at: meta.created_at.unwrap_or(file_time),
+                    updated_at: meta.updated_at.or(meta.created_at).unwrap_or(file_time),
+                },
+                _ => {
+                    let id = uuid::Uuid::new_v4().to_string();
+                    seen_ids.insert(id.clone());
+                    MemoryEntry {
+                        id,
+                        content: text,
+                        category,
+                        created_at: file_time,
+                        updated_at: file_time,
+                    }
                 }
-            }
+            };
+
+            let formatted = format_entry_line(&entry);
+            changed |= formatted != line;
+            lines.push(formatted);
+            entries.push(entry);
         }
 
-        Ok(memories)
+        if changed {
+            log_important!(info, "補上記憶条目的 ID 与時間，改寫記憶檔案: {:?}", file_path);
+            write_category_file(&file_path, &lines)?;
+        }
+
+        Ok(entries)
     }
 
     /// 獲取分類標題
     fn get_category_title(&self, category: &MemoryCategory) -> &str {
-        match category {
-            MemoryCategory::Rule => "开发规范和规则",
-            MemoryCategory::Preference => "用户偏好設定",
-            MemoryCategory::Pattern => "常用模式和最佳實務",
-            MemoryCategory::Context => "專案上下文訊息",
-        }
+        category.title()
     }
 
     /// 獲取分類檔案头部（简化版本）
@@ -304,7 +418,7 @@ impl MemoryManager {
             project_path: self.project_path.clone(),
             last_organized: Utc::now(),
             total_entries: self.get_all_memories()?.len(),
-            version: "1.0.0".to_string(),
+            version: "1.1.0".to_string(),
         };
 
         let metadata_path = self.memory_dir.join("metadata.json");
@@ -322,6 +436,14 @@ impl MemoryManager {
             return Ok("📭 暂无專案記憶".to_string());
         }
 
+        match Self::overview(&all_memories) {
+            Some(overview) => Ok(format!("📚 專案記憶总览: {}", overview)),
+            None => Ok("📭 暂无有效專案記憶".to_string()),
+        }
+    }
+
+    /// 依分類压缩汇总記憶，沒有有效內容時傳回 None
+    pub fn overview(memories: &[MemoryEntry]) -> Option<String> {
         let mut compressed_info = Vec::new();
 
         // 按分類压缩汇总
@@ -333,7 +455,7 @@ impl MemoryManager {
         ];
 
         for (category, title) in categories.iter() {
-            let memories = self.get_memories_by_category(*category)?;
+            let memories: Vec<&MemoryEntry> = memories.iter().filter(|memory| memory.category == *category).collect();
             if !memories.is_empty() {
                 let mut items = Vec::new();
                 for memory in memories {
@@ -354,9 +476,147 @@ impl MemoryManager {
         }
 
         if compressed_info.is_empty() {
-            Ok("📭 暂无有效專案記憶".to_string())
+            None
         } else {
-            Ok(format!("📚 專案記憶总览: {}", compressed_info.join(" | ")))
+            Some(compressed_info.join(" | "))
         }
     }
 }
+
+/// 寫回分類檔案，先寫暫存檔再改名，避免中斷時留下寫了一半的記憶檔案
+fn write_category_file(file_path: &Path, lines: &[String]) -> Result<()> {
+    let mut content = lines.join("\n");
+    content.push('\n');
+    let temp_path = file_path.with_extension("md.tmp");
+    fs::write(&temp_path, content)?;
+    fs::rename(&temp_path, file_path)?;
+    Ok(())
+}
+
+/// 記憶內容保存為單行，多行內容以空白連接
+fn single_line(content: &str) -> String {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// 格式化記憶条目行
+fn format_entry_line(entry: &MemoryEntry) -> String {
+    format!(
+        "- {} {}id={};created={};updated={} {}",
+        entry.content,
+        ENTRY_META_PREFIX,
+        entry.id,
+        entry.created_at.to_rfc3339_opts(SecondsFormat::Secs, true),
+        entry.updated_at.to_rfc3339_opts(SecondsFormat::Secs, true),
+        ENTRY_META_SUFFIX
+    )
+}
+
+/// 逐行解析分類檔案，``` 程式碼區塊內的行不視為記憶条目
+fn parse_entry_lines(content: &str) -> impl Iterator<Item = (&str, Option<(String, Option<EntryMeta>)>)> {
+    let mut in_fence = false;
+    content.lines().map(move |line| {
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+            return (line, None);
+        }
+        let parsed = if in_fence { None } else { parse_entry_line(line) };
+        (line, parsed)
+    })
+}
+
+/// 解析沒有縮排、`- ` 开头的記憶条目行，传回內容与行尾註解中的 ID 与時間
+///
+/// 縮排的清單項目是手動撰寫的子項目，不視為記憶条目
+fn parse_entry_line(line: &str) -> Option<(String, Option<EntryMeta>)> {
+    let rest = line.trim_end().strip_prefix("- ")?;
+
+    let (text, meta) = match rest.rfind(ENTRY_META_PREFIX) {
+        Some(start) if rest.ends_with(ENTRY_META_SUFFIX) => {
+            let raw = &rest[start + ENTRY_META_PREFIX.len()..rest.len() - ENTRY_META_SUFFIX.len()];
+            (&rest[..start], parse_entry_meta(raw))
+        }
+        _ => (rest, None),
+    };
+
+    let text = text.trim();
+    if text.is_empty() {
+        return None;
+    }
+    Some((text.to_string(), meta))
+}
+
+/// 解析 `id=...;created=...;updated=...`，缺少 ID 时传回 None
+fn parse_entry_meta(raw: &str) -> Option<EntryMeta> {
+    let mut id = None;
+    let mut created_at = None;
+    let mut updated_at = None;
+
+    for field in raw.split(';') {
+        let Some((key, value)) = field.split_once('=') else {
+            continue;
+        };
+        let value = value.trim();
+        let time = || DateTime::parse_from_rfc3339(value).ok().map(|time| time.with_timezone(&Utc));
+        match key.trim() {
+            "id" => id = Some(value.to_string()),
+            "created" => created_at = time(),
+            "updated" => updated_at = time(),
+            _ => {}
+        }
+    }
+
+    let id = id.filter(|id| !id.is_empty() && !id.contains(char::is_whitespace))?;
+    Some(EntryMeta { id, created_at, updated_at })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entry_line_round_trip() {
+        // 舊格式的行沒有註解
+        let (text, meta) = parse_entry_line("- 使用 4 個空白縮排").unwrap();
+        assert_eq!(text, "使用 4 個空白縮排");
+        assert!(meta.is_none());
+
+        let created_at = DateTime::parse_from_rfc3339("2024-05-01T08:00:00Z").unwrap().with_timezone(&Utc);
+        let entry = MemoryEntry {
+            id: "abc-123".to_string(),
+            content: text,
+            category: MemoryCategory::Rule,
+            created_at,
+            updated_at: created_at,
+        };
+        let line = format_entry_line(&entry);
+        assert_eq!(
+            line,
+            "- 使用 4 個空白縮排 <!-- cunzhi:id=abc-123;created=2024-05-01T08:00:00Z;updated=2024-05-01T08:00:00Z -->"
+        );
+
+        let (text, meta) = parse_entry_line(&line).unwrap();
+        let meta = meta.unwrap();
+        assert_eq!(text, "使用 4 個空白縮排");
+        assert_eq!(meta.id, "abc-123");
+        assert_eq!(meta.created_at, Some(created_at));
+
+        // 沒有 ID 的註解視為舊格式，空白条目略過
+        assert!(parse_entry_line("- 內容 <!-- cunzhi:created=2024-05-01T08:00:00Z -->").unwrap().1.is_none());
+        assert!(parse_entry_line("- ").is_none());
+        assert!(parse_entry_line("# 標題").is_none());
+    }
+
+    #[test]
+    fn test_entry_lines_skip_code_and_nested_items() {
+        let content = "# 開發規範\n\n- 使用 4 個空白縮排\n  - 縮排的子項目\n```markdown\n- 範例中的清單\n```\n- 提交前執行測試";
+        let entries: Vec<String> = parse_entry_lines(content)
+            .filter_map(|(_, parsed)| parsed.map(|(text, _)| text))
+            .collect();
+        assert_eq!(entries, vec!["使用 4 個空白縮排", "提交前執行測試"]);
+    }
+}
@@ -1,8 +1,13 @@
 use anyhow::Result;
 use rmcp::{ErrorData as McpError, model::*};
+use std::collections::HashMap;
+use std::sync::Arc;
 
-use super::{MemoryManager, MemoryCategory};
-use crate::mcp::{JiyiRequest, utils::{validate_project_path, project_path_error}};
+use super::search::similarity;
+use super::{AddMemoryOutcome, MemoryManager, MemoryCategory, MemoryEntry, MemoryIndex};
+use crate::config::AppConfig;
+use crate::constants::mcp::{DEFAULT_MEMORY_RECALL_LIMIT, MEMORY_DUPLICATE_SIMILARITY, MEMORY_RECALL_MAX_LIMIT};
+use crate::mcp::{JiyiAction, JiyiRequest, MemoryScope, utils::{validate_project_path, project_path_error}};
 
 /// 全局記憶管理工具
 ///
@@ -13,50 +18,287 @@ pub struct MemoryTool;
 impl MemoryTool {
     pub async fn jiyi(
         request: JiyiRequest,
+        config: Arc<AppConfig>,
     ) -> Result<CallToolResult, McpError> {
-        // 使用增强的路径驗證功能
-        if let Err(e) = validate_project_path(&request.project_path) {
-            return Err(project_path_error(format!(
-                "路径驗證失敗: {}\n原始路径: {}\n請檢查路径格式是否正确，特別是 Windows 路径应使用正确的盘符格式（如 C:\\path）",
-                e,
-                request.project_path
-            )).into());
-        }
-
-        let manager = MemoryManager::new(&request.project_path)
-            .map_err(|e| McpError::internal_error(format!("建立記憶管理器失敗: {}", e), None))?;
+        // 新增記憶預設寫入專案，其他操作預設同時查看全域与專案記憶
+        let scope = request.scope.unwrap_or(match request.action {
+            JiyiAction::Remember => MemoryScope::Project,
+            _ => MemoryScope::Both,
+        });
+        let layers = MemoryLayers::open(&request.project_path, scope)?;
 
-        let result = match request.action.as_str() {
-            "記憶" => {
+        let result = match request.action {
+            JiyiAction::Remember => {
                 if request.content.trim().is_empty() {
                     return Err(McpError::invalid_params("缺少記憶內容".to_string(), None));
                 }
-
-                let category = match request.category.as_str() {
-                    "rule" => MemoryCategory::Rule,
-                    "preference" => MemoryCategory::Preference,
-                    "pattern" => MemoryCategory::Pattern,
-                    "context" => MemoryCategory::Context,
-                    _ => MemoryCategory::Context,
+                let [(scope, manager)] = layers.layers.as_slice() else {
+                    return Err(McpError::invalid_params("記憶時 scope 只能是 global 或 project".to_string(), None));
                 };
 
-                let id = manager.add_memory(&request.content, category)
+                // 未指定分類時歸入專案上下文，無法辨識的分類与其他操作一樣傳回錯誤
+                let category = optional_category(&request)?.unwrap_or(MemoryCategory::Context);
+
+                let outcome = manager.add_memory(&request.content, category, config.mcp_config.memory_auto_merge)
                     .map_err(|e| McpError::internal_error(format!("新增記憶失敗: {}", e), None))?;
 
-                format!("✅ 記憶已新增，ID: {}\n📝 內容: {}\n📂 分類: {:?}", id, request.content, category)
+                match outcome {
+                    AddMemoryOutcome::Added(entry) => format!(
+                        "✅ 記憶已新增，ID: {}\n📝 內容: {}\n📂 分類: {}\n🌐 範圍: {}",
+                        entry.id,
+                        entry.content,
+                        category.name(),
+                        scope.label()
+                    ),
+                    AddMemoryOutcome::Duplicate { existing, similarity } => format!(
+                        "⚠️ 已有相近的記憶（相似度 {:.0}%），未新增\n{}\n💡 如需修正內容，請以 update 修改 ID {}",
+                        similarity * 100.0,
+                        entry_detail(&existing, *scope),
+                        existing.id
+                    ),
+                    AddMemoryOutcome::Merged { previous, merged } => format!(
+                        "🔀 已合併到相近的記憶\n📝 原內容: {}\n{}",
+                        previous.content,
+                        entry_detail(&merged, *scope)
+                    ),
+                }
+            }
+            JiyiAction::Recall => {
+                let memories = layers.load(optional_category(&request)?)?;
+
+                match request.query.as_deref().map(str::trim).filter(|query| !query.is_empty()) {
+                    Some(query) => {
+                        let scopes: HashMap<String, MemoryScope> = memories
+                            .iter()
+                            .map(|(scope, entry)| (entry.id.clone(), *scope))
+                            .collect();
+                        let entries: Vec<MemoryEntry> = memories.into_iter().map(|(_, entry)| entry).collect();
+
+                        let limit = request
+                            .limit
+                            .unwrap_or(DEFAULT_MEMORY_RECALL_LIMIT)
+                            .clamp(1, MEMORY_RECALL_MAX_LIMIT);
+                        let results = MemoryIndex::new(&entries).search(query, limit);
+
+                        if results.is_empty() {
+                            format!("📭 沒有与「{}」相關的記憶（共 {} 條，可用 list 查看全部）", query, entries.len())
+                        } else {
+                            let lines: Vec<String> = results
+                                .iter()
+                                .map(|(entry, _)| entry_line(entry, scopes[&entry.id]))
+                                .collect();
+                            format!(
+                                "🔎 与「{}」最相關的 {} 條記憶（共 {} 條）:\n{}",
+                                query,
+                                results.len(),
+                                entries.len(),
+                                lines.join("\n")
+                            )
+                        }
+                    }
+                    None => {
+                        let overview = |layer: MemoryScope| {
+                            let entries: Vec<MemoryEntry> = memories
+                                .iter()
+                                .filter(|(scope, _)| *scope == layer)
+                                .map(|(_, entry)| entry.clone())
+                                .collect();
+                            MemoryManager::overview(&entries)
+                        };
+
+                        let mut sections = Vec::new();
+                        if let Some(project) = overview(MemoryScope::Project) {
+                            sections.push(format!("📚 專案記憶总览: {}", project));
+                        }
+                        if let Some(global) = overview(MemoryScope::Global) {
+                            if layers.layers.len() > 1 {
+                                sections.push(format!("🌐 全域記憶总览（与專案記憶衝突時以專案記憶為準）: {}", global));
+                            } else {
+                                sections.push(format!("🌐 全域記憶总览: {}", global));
+                            }
+                        }
+
+                        if sections.is_empty() {
+                            format!("📭 暂无{}記憶", scope.label())
+                        } else {
+                            sections.join("\n")
+                        }
+                    }
+                }
+            }
+            JiyiAction::List => {
+                let memories = layers.load(optional_category(&request)?)?;
+
+                if memories.is_empty() {
+                    format!("📭 暂无{}記憶", scope.label())
+                } else {
+                    let lines: Vec<String> = memories.iter().map(|(scope, entry)| entry_line(entry, *scope)).collect();
+                    format!("📋 共 {} 條記憶（專案在前，依更新時間排序）:\n{}", memories.len(), lines.join("\n"))
+                }
+            }
+            JiyiAction::Get => {
+                let id = required_id(&request)?;
+                let (scope, manager) = layers.find(id)?;
+                let entry = manager.get_memory(id)
+                    .map_err(|e| McpError::internal_error(format!("讀取記憶失敗: {}", e), None))?
+                    .ok_or_else(|| not_found(id))?;
+                entry_detail(&entry, scope)
             }
-            "回忆" | "回憶" => {
-                manager.get_project_info()
-                    .map_err(|e| McpError::internal_error(format!("獲取專案訊息失敗: {}", e), None))?
+            JiyiAction::Update => {
+                let id = required_id(&request)?;
+                if request.content.trim().is_empty() {
+                    return Err(McpError::invalid_params("缺少新的記憶內容".to_string(), None));
+                }
+                let (scope, manager) = layers.find(id)?;
+                let entry = manager.update_memory(id, &request.content)
+                    .map_err(|e| McpError::internal_error(format!("修改記憶失敗: {}", e), None))?
+                    .ok_or_else(|| not_found(id))?;
+                format!("✏️ 記憶已修改\n{}", entry_detail(&entry, scope))
             }
-            _ => {
-                return Err(McpError::invalid_params(
-                    format!("未知的操作類型: {}（支援：記憶/記憶、回忆/回憶）", request.action),
-                    None
-                ));
+            JiyiAction::Delete => {
+                let id = required_id(&request)?;
+                let (_, manager) = layers.find(id)?;
+                let entry = manager.delete_memory(id)
+                    .map_err(|e| McpError::internal_error(format!("刪除記憶失敗: {}", e), None))?
+                    .ok_or_else(|| not_found(id))?;
+                format!("🗑️ 記憶已刪除，ID: {}\n📝 內容: {}", entry.id, entry.content)
+            }
+            JiyiAction::MoveCategory => {
+                let id = required_id(&request)?;
+                let category = optional_category(&request)?
+                    .ok_or_else(|| McpError::invalid_params("move-category 需要目標分類 category".to_string(), None))?;
+                let (scope, manager) = layers.find(id)?;
+                let entry = manager.move_memory(id, category)
+                    .map_err(|e| McpError::internal_error(format!("移動記憶失敗: {}", e), None))?
+                    .ok_or_else(|| not_found(id))?;
+                format!("📂 記憶已移到 {}\n{}", category.name(), entry_detail(&entry, scope))
             }
         };
 
         Ok(CallToolResult::success(vec![Content::text(result)]))
     }
 }
+
+/// 依 scope 開啟的記憶層，專案層在前
+struct MemoryLayers {
+    layers: Vec<(MemoryScope, MemoryManager)>,
+}
+
+impl MemoryLayers {
+    fn open(project_path: &str, scope: MemoryScope) -> Result<Self, McpError> {
+        let mut layers = Vec::new();
+
+        if scope != MemoryScope::Global {
+            // 使用增强的路径驗證功能
+            if let Err(e) = validate_project_path(project_path) {
+                return Err(project_path_error(format!(
+                    "路径驗證失敗: {}\n原始路径: {}\n請檢查路径格式是否正确，特別是 Windows 路径应使用正确的盘符格式（如 C:\\path）",
+                    e,
+                    project_path
+                )).into());
+            }
+
+            let manager = MemoryManager::new(project_path)
+                .map_err(|e| McpError::internal_error(format!("建立記憶管理器失敗: {}", e), None))?;
+            layers.push((MemoryScope::Project, manager));
+        }
+
+        if scope != MemoryScope::Project {
+            let manager = MemoryManager::global()
+                .map_err(|e| McpError::internal_error(format!("建立全域記憶管理器失敗: {}", e), None))?;
+            layers.push((MemoryScope::Global, manager));
+        }
+
+        Ok(Self { layers })
+    }
+
+    /// 讀取並合併各層的記憶，專案在前；与專案記憶相近的全域記憶以專案記憶為準而略過
+    fn load(&self, category: Option<MemoryCategory>) -> Result<Vec<(MemoryScope, MemoryEntry)>, McpError> {
+        let mut merged: Vec<(MemoryScope, MemoryEntry)> = Vec::new();
+
+        for (scope, manager) in &self.layers {
+            let memories = match category {
+                Some(category) => manager.get_memories_by_category(category),
+                None => manager.get_all_memories(),
+            }
+            .map_err(|e| McpError::internal_error(format!("讀取{}記憶失敗: {}", scope.label(), e), None))?;
+
+            for entry in memories {
+                let overridden = merged.iter().any(|(existing_scope, existing)| {
+                    existing_scope != scope && similarity(&existing.content, &entry.content) >= MEMORY_DUPLICATE_SIMILARITY
+                });
+                if !overridden {
+                    merged.push((*scope, entry));
+                }
+            }
+        }
+
+        Ok(merged)
+    }
+
+    /// 找出記憶 ID 所在的層
+    fn find(&self, id: &str) -> Result<(MemoryScope, &MemoryManager), McpError> {
+        for (scope, manager) in &self.layers {
+            let found = manager
+                .get_memory(id)
+                .map_err(|e| McpError::internal_error(format!("讀取{}記憶失敗: {}", scope.label(), e), None))?
+                .is_some();
+            if found {
+                return Ok((*scope, manager));
+            }
+        }
+        Err(not_found(id))
+    }
+}
+
+/// 依 ID 操作時必需的記憶 ID
+fn required_id(request: &JiyiRequest) -> Result<&str, McpError> {
+    request
+        .id
+        .as_deref()
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+        .ok_or_else(|| McpError::invalid_params("缺少記憶 ID（可用 list 查詢）".to_string(), None))
+}
+
+/// 解析可選的分類參數，提供了但無法辨識時傳回錯誤
+fn optional_category(request: &JiyiRequest) -> Result<Option<MemoryCategory>, McpError> {
+    match request.category.as_deref().map(str::trim).filter(|name| !name.is_empty()) {
+        Some(name) => MemoryCategory::parse(name).map(Some).ok_or_else(|| {
+            McpError::invalid_params(
+                format!("未知的記憶分類: {}（支援：rule、preference、pattern、context）", name),
+                None,
+            )
+        }),
+        None => Ok(None),
+    }
+}
+
+fn not_found(id: &str) -> McpError {
+    McpError::invalid_params(format!("找不到記憶: {}（可用 list 查詢現有記憶）", id), None)
+}
+
+/// 列表中的單行記憶
+fn entry_line(entry: &MemoryEntry, scope: MemoryScope) -> String {
+    format!(
+        "- [{}] {}（{}，{}，更新於 {}）",
+        entry.id,
+        entry.content,
+        entry.category.name(),
+        scope.label(),
+        entry.updated_at.format("%Y-%m-%d %H:%M")
+    )
+}
+
+/// 單一記憶的完整資訊
+fn entry_detail(entry: &MemoryEntry, scope: MemoryScope) -> String {
+    format!(
+        "🆔 ID: {}\n📝 內容: {}\n📂 分類: {}\n🌐 範圍: {}\n🕒 建立: {}\n🕒 更新: {}",
+        entry.id,
+        entry.content,
+        entry.category.name(),
+        scope.label(),
+        entry.created_at.to_rfc3339(),
+        entry.updated_at.to_rfc3339()
+    )
+}
@@ -4,9 +4,11 @@
 
 pub mod manager;
 pub mod types;
+pub mod search;
 pub mod mcp;
 
 // 重新匯出主要類型和功能
 pub use manager::MemoryManager;
-pub use types::{MemoryEntry, MemoryCategory, MemoryMetadata};
+pub use types::{AddMemoryOutcome, MemoryEntry, MemoryCategory, MemoryMetadata};
+pub use search::MemoryIndex;
 pub use mcp::MemoryTool;
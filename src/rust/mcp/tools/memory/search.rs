@@ -0,0 +1,233 @@
+//! 記憶的全文檢索与相似度比對
+//!
+//! 以 BM25 為記憶條目評分；英數字以單字為詞，中日韓文字以單字与相鄰兩字為詞，
+//! 不需要分詞詞典也能比對中文內容。新增記憶時以正規化文字的相似度找出重複的條目
+
+use std::collections::HashMap;
+
+use super::types::MemoryEntry;
+
+/// BM25 的詞頻飽和參數
+const BM25_K1: f64 = 1.2;
+/// BM25 的文件長度正規化參數
+const BM25_B: f64 = 0.75;
+
+/// 記憶條目的倒排索引
+pub struct MemoryIndex<'a> {
+    entries: &'a [MemoryEntry],
+    /// 每個條目的詞頻
+    term_freqs: Vec<HashMap<String, usize>>,
+    /// 每個條目的詞數
+    lengths: Vec<usize>,
+    average_length: f64,
+    /// 包含各詞的條目數
+    doc_freqs: HashMap<String, usize>,
+}
+
+impl<'a> MemoryIndex<'a> {
+    pub fn new(entries: &'a [MemoryEntry]) -> Self {
+        let mut term_freqs = Vec::with_capacity(entries.len());
+        let mut lengths = Vec::with_capacity(entries.len());
+        let mut doc_freqs: HashMap<String, usize> = HashMap::new();
+
+        for entry in entries {
+            let tokens = tokenize(&entry.content);
+            lengths.push(tokens.len());
+            let mut freqs: HashMap<String, usize> = HashMap::new();
+            for token in tokens {
+                *freqs.entry(token).or_default() += 1;
+            }
+            for term in freqs.keys() {
+                *doc_freqs.entry(term.clone()).or_default() += 1;
+            }
+            term_freqs.push(freqs);
+        }
+
+        let average_length = if entries.is_empty() {
+            0.0
+        } else {
+            lengths.iter().sum::<usize>() as f64 / entries.len() as f64
+        };
+
+        Self { entries, term_freqs, lengths, average_length, doc_freqs }
+    }
+
+    /// 依相關度由高到低傳回最多 `limit` 個符合的條目，分數為 0 的條目不列入
+    pub fn search(&self, query: &str, limit: usize) -> Vec<(&'a MemoryEntry, f64)> {
+        let mut terms = tokenize(query);
+        terms.sort();
+        terms.dedup();
+
+        let count = self.entries.len() as f64;
+        let mut results: Vec<(&'a MemoryEntry, f64)> = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter_map(|(index, entry)| {
+                let score: f64 = terms
+                    .iter()
+                    .filter_map(|term| {
+                        let freq = *self.term_freqs[index].get(term)? as f64;
+                        let doc_freq = self.doc_freqs[term] as f64;
+                        let idf = (1.0 + (count - doc_freq + 0.5) / (doc_freq + 0.5)).ln();
+                        let length_ratio = if self.average_length > 0.0 {
+                            self.lengths[index] as f64 / self.average_length
+                        } else {
+                            1.0
+                        };
+                        Some(idf * freq * (BM25_K1 + 1.0) / (freq + BM25_K1 * (1.0 - BM25_B + BM25_B * length_ratio)))
+                    })
+                    .sum();
+                (score > 0.0).then_some((entry, score))
+            })
+            .collect();
+
+        // 分數相同時較新的條目在前
+        results.sort_by(|(a, a_score), (b, b_score)| {
+            b_score.total_cmp(a_score).then_with(|| b.updated_at.cmp(&a.updated_at))
+        });
+        results.truncate(limit);
+        results
+    }
+}
+
+/// 切分檢索詞：英數字連續片段轉小寫為一個詞，中日韓文字取每個單字与相鄰兩字
+///
+/// 單字也列入，只有一個字的檢索詞（例如「中」）才能比對到「中文」這樣較長的片段
+pub fn tokenize(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut word = String::new();
+    let mut cjk_run: Vec<char> = Vec::new();
+
+    let flush_word = |word: &mut String, tokens: &mut Vec<String>| {
+        if !word.is_empty() {
+            tokens.push(std::mem::take(word));
+        }
+    };
+    let flush_cjk = |run: &mut Vec<char>, tokens: &mut Vec<String>| {
+        tokens.extend(run.iter().map(char::to_string));
+        tokens.extend(run.windows(2).map(|pair| pair.iter().collect::<String>()));
+        run.clear();
+    };
+
+    for c in text.chars() {
+        if is_cjk(c) {
+            flush_word(&mut word, &mut tokens);
+            cjk_run.push(c);
+        } else if c.is_alphanumeric() {
+            flush_cjk(&mut cjk_run, &mut tokens);
+            word.extend(c.to_lowercase());
+        } else {
+            flush_word(&mut word, &mut tokens);
+            flush_cjk(&mut cjk_run, &mut tokens);
+        }
+    }
+    flush_word(&mut word, &mut tokens);
+    flush_cjk(&mut cjk_run, &mut tokens);
+
+    tokens
+}
+
+/// 比對重複用的正規化文字：轉小寫並移除空白与標點
+pub fn normalize_text(text: &str) -> String {
+    text.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// 兩段文字的相似度（0 到 1），以正規化後相鄰兩字的 Dice 係數計算，
+/// 對語序微調、增刪幾個字的改寫仍有高分
+pub fn similarity(a: &str, b: &str) -> f64 {
+    let a: Vec<char> = normalize_text(a).chars().collect();
+    let b: Vec<char> = normalize_text(b).chars().collect();
+    if a == b {
+        return 1.0;
+    }
+    if a.len() < 2 || b.len() < 2 {
+        return 0.0;
+    }
+
+    let mut bigrams: HashMap<(char, char), usize> = HashMap::new();
+    for pair in a.windows(2) {
+        *bigrams.entry((pair[0], pair[1])).or_default() += 1;
+    }
+    let mut shared = 0;
+    for pair in b.windows(2) {
+        if let Some(count) = bigrams.get_mut(&(pair[0], pair[1])).filter(|count| **count > 0) {
+            *count -= 1;
+            shared += 1;
+        }
+    }
+
+    2.0 * shared as f64 / (a.len() - 1 + b.len() - 1) as f64
+}
+
+/// 中日韓文字（統一表意文字、假名与諺文）
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30ff}'     // 平假名、片假名
+        | '\u{3400}'..='\u{4dbf}'   // 擴充 A
+        | '\u{4e00}'..='\u{9fff}'   // 統一表意文字
+        | '\u{ac00}'..='\u{d7af}'   // 諺文音節
+        | '\u{f900}'..='\u{faff}'   // 相容表意文字
+        | '\u{20000}'..='\u{2ffff}' // 擴充 B 之後
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::tools::memory::MemoryCategory;
+    use chrono::Utc;
+
+    fn entry(id: &str, content: &str) -> MemoryEntry {
+        MemoryEntry {
+            id: id.to_string(),
+            content: content.to_string(),
+            category: MemoryCategory::Rule,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_tokenize() {
+        assert_eq!(tokenize("Use pnpm_v9, not NPM!"), vec!["use", "pnpm", "v9", "not", "npm"]);
+        assert_eq!(tokenize("使用中文"), vec!["使", "用", "中", "文", "使用", "用中", "中文"]);
+        assert_eq!(tokenize("用 Rust 寫"), vec!["用", "rust", "寫"]);
+    }
+
+    #[test]
+    fn test_search_ranking() {
+        let entries = vec![
+            entry("a", "提交訊息使用英文"),
+            entry("b", "套件管理一律使用 pnpm，不要用 npm"),
+            entry("c", "回答一律使用繁體中文"),
+        ];
+        let index = MemoryIndex::new(&entries);
+
+        let results = index.search("pnpm", 5);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].0.id, "b");
+
+        let results = index.search("繁體中文回答", 5);
+        assert_eq!(results[0].0.id, "c");
+
+        // 單字的檢索詞也能比對較長的中文片段
+        let results = index.search("繁", 5);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].0.id, "c");
+
+        assert!(index.search("docker", 5).is_empty());
+        assert_eq!(index.search("使用", 2).len(), 2);
+    }
+
+    #[test]
+    fn test_similarity() {
+        assert_eq!(similarity("Use pnpm, not npm.", "use  PNPM not npm"), 1.0);
+        assert!(similarity("回答一律使用繁體中文", "回答請一律使用繁體中文") >= 0.8);
+        assert!(similarity("Always use pnpm instead of npm", "Always use pnpm instead of npm or yarn") >= 0.8);
+        assert!(similarity("提交訊息使用英文", "回答一律使用繁體中文") < 0.8);
+    }
+}
@@ -11,6 +11,17 @@ pub struct MemoryEntry {
     pub updated_at: DateTime<Utc>,
 }
 
+/// 新增記憶的結果
+#[derive(Debug, Clone)]
+pub enum AddMemoryOutcome {
+    /// 已新增為新的記憶
+    Added(MemoryEntry),
+    /// 同分類已有相近的記憶，未新增
+    Duplicate { existing: MemoryEntry, similarity: f64 },
+    /// 已以新內容取代相近的記憶
+    Merged { previous: MemoryEntry, merged: MemoryEntry },
+}
+
 /// 記憶分類
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
 pub enum MemoryCategory {
@@ -20,6 +31,64 @@ pub enum MemoryCategory {
     Context,     // 專案上下文訊息
 }
 
+impl MemoryCategory {
+    /// 所有記憶分類（依顯示順序）
+    pub const ALL: [MemoryCategory; 4] = [
+        MemoryCategory::Rule,
+        MemoryCategory::Preference,
+        MemoryCategory::Pattern,
+        MemoryCategory::Context,
+    ];
+
+    /// 分類對應的記憶檔案名稱
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            MemoryCategory::Rule => "rules.md",
+            MemoryCategory::Preference => "preferences.md",
+            MemoryCategory::Pattern => "patterns.md",
+            MemoryCategory::Context => "context.md",
+        }
+    }
+
+    /// 分類在資源 URI 中使用的名稱（即檔案名稱去掉副檔名）
+    pub fn slug(&self) -> &'static str {
+        self.file_name().trim_end_matches(".md")
+    }
+
+    /// 由資源 URI 中的名稱解析分類
+    pub fn from_slug(slug: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|category| category.slug() == slug)
+    }
+
+    /// 分類在 `ji` 參數中使用的名稱
+    pub fn name(&self) -> &'static str {
+        match self {
+            MemoryCategory::Rule => "rule",
+            MemoryCategory::Preference => "preference",
+            MemoryCategory::Pattern => "pattern",
+            MemoryCategory::Context => "context",
+        }
+    }
+
+    /// 由 `ji` 參數解析分類，也接受資源 URI 中的名稱（如 rules）
+    pub fn parse(name: &str) -> Option<Self> {
+        let name = name.trim().to_ascii_lowercase();
+        Self::ALL
+            .into_iter()
+            .find(|category| category.name() == name || category.slug() == name)
+    }
+
+    /// 分類標題
+    pub fn title(&self) -> &'static str {
+        match self {
+            MemoryCategory::Rule => "开发规范和规则",
+            MemoryCategory::Preference => "用户偏好設定",
+            MemoryCategory::Pattern => "常用模式和最佳實務",
+            MemoryCategory::Context => "專案上下文訊息",
+        }
+    }
+}
+
 /// 記憶元資料
 #[derive(Debug, Serialize, Deserialize)]
 pub struct MemoryMetadata {
@@ -0,0 +1,280 @@
+use anyhow::Result;
+use axum::{
+    extract::{Request, State},
+    http::{header, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    Router,
+};
+use rmcp::transport::streamable_http_server::{
+    session::local::LocalSessionManager, StreamableHttpServerConfig, StreamableHttpService,
+};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+
+use super::config_watcher::ConfigCache;
+use super::server::ZhiServer;
+use crate::constants::mcp;
+use crate::log_important;
+
+/// MCP 傳輸方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransportKind {
+    /// 標準輸入輸出，每個客戶端各自啟動一個行程
+    Stdio,
+    /// Streamable HTTP，多個客戶端共用一個常駐伺服器
+    Http,
+}
+
+/// 傳輸設定（由命令列參數解析）
+#[derive(Debug, Clone)]
+pub struct TransportOptions {
+    pub kind: TransportKind,
+    pub listen: SocketAddr,
+    pub auth_token: Option<String>,
+}
+
+impl Default for TransportOptions {
+    fn default() -> Self {
+        Self {
+            kind: TransportKind::Stdio,
+            listen: mcp::DEFAULT_HTTP_LISTEN
+                .parse()
+                .expect("預設監聽位址格式錯誤"),
+            auth_token: None,
+        }
+    }
+}
+
+impl TransportOptions {
+    /// 解析命令列參數
+    ///
+    /// 支援 `--transport stdio|http`、`--listen <位址>` 與 `--auth-token <權杖>`；
+    /// 權杖建議以 `CUNZHI_MCP_TOKEN` 環境變數提供，命令列參數會被其他本機使用者從程序列表看到
+    pub fn from_args<I>(args: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut options = Self::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--transport" => {
+                    let value = args.next().ok_or_else(|| anyhow::anyhow!("--transport 缺少參數值"))?;
+                    options.kind = match value.as_str() {
+                        "stdio" => TransportKind::Stdio,
+                        "http" => TransportKind::Http,
+                        other => anyhow::bail!("不支援的傳輸方式: {}（支援：stdio、http）", other),
+                    };
+                }
+                "--listen" => {
+                    let value = args.next().ok_or_else(|| anyhow::anyhow!("--listen 缺少參數值"))?;
+                    options.listen = value
+                        .parse()
+                        .map_err(|e| anyhow::anyhow!("監聽位址格式錯誤 {}: {}", value, e))?;
+                }
+                "--auth-token" => {
+                    let value = args.next().ok_or_else(|| anyhow::anyhow!("--auth-token 缺少參數值"))?;
+                    log_important!(
+                        warn,
+                        "--auth-token 的權杖會出現在程序列表中，其他本機使用者也能看到，建議改用 {} 環境變數",
+                        mcp::HTTP_AUTH_TOKEN_ENV
+                    );
+                    options.auth_token = Some(value);
+                }
+                other => anyhow::bail!("未知參數: {}", other),
+            }
+        }
+
+        if options.auth_token.is_none() {
+            options.auth_token = std::env::var(mcp::HTTP_AUTH_TOKEN_ENV)
+                .ok()
+                .filter(|token| !token.trim().is_empty());
+        }
+
+        Ok(options)
+    }
+}
+
+/// 以 Streamable HTTP 方式啟動 MCP 伺服器
+///
+/// 每個 HTTP 工作階段都會建立獨立的 `ZhiServer`，直到收到 Ctrl+C 為止；
+/// 未設定權杖時只接受本機的 Host 与 Origin，避免網頁透過 DNS rebinding 呼叫寸止
+pub async fn serve_http(options: &TransportOptions) -> Result<()> {
+    if options.auth_token.is_none() && !options.listen.ip().is_loopback() {
+        log_important!(
+            warn,
+            "HTTP 傳輸監聽在非本機位址 {} 且未設定認證權杖：只接受以 localhost 或 IP 位址連線、且不是來自其他網站的請求，但網路上能連到此位址的任何人仍能呼叫寸止",
+            options.listen
+        );
+    }
+
+    // 所有工作階段共用同一份設定快取，設定變更時一併通知
+    let config = Arc::new(ConfigCache::new());
+    let service = StreamableHttpService::new(
+        move || Ok(ZhiServer::with_config(Arc::clone(&config))),
+        LocalSessionManager::default().into(),
+        StreamableHttpServerConfig::default(),
+    );
+
+    let mut router = Router::new().nest_service(mcp::HTTP_ENDPOINT_PATH, service);
+    if let Some(token) = &options.auth_token {
+        router = router.layer(middleware::from_fn_with_state(
+            Arc::new(token.clone()),
+            require_bearer_token,
+        ));
+    } else {
+        router = router.layer(middleware::from_fn(require_local_origin));
+    }
+
+    let listener = tokio::net::TcpListener::bind(options.listen)
+        .await
+        .map_err(|e| anyhow::anyhow!("無法監聽 {}: {}", options.listen, e))?;
+
+    log_important!(
+        info,
+        "MCP HTTP 伺服器已啟動: http://{}{}",
+        options.listen,
+        mcp::HTTP_ENDPOINT_PATH
+    );
+
+    axum::serve(listener, router)
+        .with_graceful_shutdown(async {
+            let _ = tokio::signal::ctrl_c().await;
+            log_important!(info, "收到中斷訊號，正在關閉 MCP HTTP 伺服器");
+        })
+        .await?;
+
+    Ok(())
+}
+
+/// 驗證 `Authorization: Bearer <權杖>` 標頭
+async fn require_bearer_token(
+    State(expected): State<Arc<String>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let provided = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim);
+
+    match provided {
+        Some(token) if constant_time_eq(token.as_bytes(), expected.as_bytes()) => {
+            next.run(request).await
+        }
+        _ => (
+            StatusCode::UNAUTHORIZED,
+            [(header::WWW_AUTHENTICATE, "Bearer")],
+            "缺少或無效的認證權杖",
+        )
+            .into_response(),
+    }
+}
+
+/// 檢查 `Host` 与 `Origin` 標頭
+///
+/// DNS rebinding 需要把攻擊者的網域解析到本機，因此 Host 只接受 localhost 或 IP 位址；
+/// 瀏覽器送出的 Origin 必須是本機，非瀏覽器的客戶端不會帶 Origin
+async fn require_local_origin(request: Request, next: Next) -> Response {
+    let headers = request.headers();
+    let host = headers
+        .get(header::HOST)
+        .and_then(|value| value.to_str().ok())
+        .or_else(|| request.uri().authority().map(|authority| authority.as_str()));
+    let host_allowed = host.is_some_and(|host| {
+        let name = host_name(host);
+        name.eq_ignore_ascii_case("localhost") || name.parse::<IpAddr>().is_ok()
+    });
+    let origin_allowed = match headers.get(header::ORIGIN) {
+        None => true,
+        Some(origin) => origin.to_str().ok().and_then(origin_host).is_some_and(is_loopback_host),
+    };
+
+    if host_allowed && origin_allowed {
+        next.run(request).await
+    } else {
+        log_important!(warn, "拒絕非本機來源的 HTTP 請求: Host={:?}, Origin={:?}", host, headers.get(header::ORIGIN));
+        (StatusCode::FORBIDDEN, "未設定認證權杖時只接受來自本機的請求").into_response()
+    }
+}
+
+/// 去掉連接埠的主機名稱，IPv6 位址去掉方括號
+fn host_name(authority: &str) -> &str {
+    if let Some(rest) = authority.strip_prefix('[') {
+        return rest.split(']').next().unwrap_or(rest);
+    }
+    authority.rsplit_once(':').map_or(authority, |(host, _)| host)
+}
+
+/// Origin 的主機名稱，例如 `http://localhost:5173` 的 `localhost`；`null` 等無法解析的傳回 None
+fn origin_host(origin: &str) -> Option<&str> {
+    let (_, rest) = origin.split_once("://")?;
+    Some(host_name(rest.split('/').next()?))
+}
+
+/// 是否為 localhost 或迴路位址
+fn is_loopback_host(host: &str) -> bool {
+    host.eq_ignore_ascii_case("localhost") || host.parse::<IpAddr>().is_ok_and(|ip| ip.is_loopback())
+}
+
+/// 固定時間比較，避免透過回應時間推測權杖
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b.iter()).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<TransportOptions> {
+        TransportOptions::from_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn test_transport_options_from_args() {
+        let options = parse(&["--transport", "http", "--listen", "0.0.0.0:9000", "--auth-token", "secret"]).unwrap();
+        assert_eq!(options.kind, TransportKind::Http);
+        assert_eq!(options.listen, "0.0.0.0:9000".parse().unwrap());
+        assert_eq!(options.auth_token.as_deref(), Some("secret"));
+
+        let options = parse(&[]).unwrap();
+        assert_eq!(options.kind, TransportKind::Stdio);
+        assert_eq!(options.listen, mcp::DEFAULT_HTTP_LISTEN.parse().unwrap());
+
+        assert!(parse(&["--transport", "sse"]).is_err());
+        assert!(parse(&["--listen", "localhost"]).is_err());
+        assert!(parse(&["--auth-token"]).is_err());
+        assert!(parse(&["--verbose"]).is_err());
+    }
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq(b"secret", b"secret"));
+        assert!(constant_time_eq(b"", b""));
+        assert!(!constant_time_eq(b"secret", b"secreT"));
+        assert!(!constant_time_eq(b"secret", b"secret2"));
+    }
+
+    #[test]
+    fn test_local_hosts() {
+        assert_eq!(host_name("localhost:8848"), "localhost");
+        assert_eq!(host_name("127.0.0.1"), "127.0.0.1");
+        assert_eq!(host_name("[::1]:8848"), "::1");
+
+        assert_eq!(origin_host("http://localhost:5173"), Some("localhost"));
+        assert_eq!(origin_host("https://evil.example/path"), Some("evil.example"));
+        assert_eq!(origin_host("null"), None);
+
+        assert!(is_loopback_host("LOCALHOST"));
+        assert!(is_loopback_host("::1"));
+        assert!(!is_loopback_host("192.168.1.2"));
+        assert!(!is_loopback_host("evil.example"));
+    }
+}
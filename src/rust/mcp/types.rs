@@ -1,71 +1,687 @@
 use chrono;
+use rmcp::model::JsonObject;
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::mcp::utils::{schema_object_for, DiffFile, FormField, PopupAttachment};
 
 #[derive(Debug, Deserialize, schemars::JsonSchema)]
 pub struct ZhiRequest {
     #[schemars(description = "要顯示給使用者的訊息")]
     pub message: String,
-    #[schemars(description = "預定義的選項列表（可選）")]
+    #[schemars(description = "預定義的選項列表（可選），每個選項可為文字，或含 id、label、description、recommended、exclusive 的物件")]
+    #[serde(default)]
+    pub predefined_options: Vec<PredefinedOptionInput>,
+    #[schemars(description = "選擇模式：single（單選）或 multiple（多選），預設為 multiple")]
     #[serde(default)]
-    pub predefined_options: Vec<String>,
+    pub selection_mode: SelectionMode,
     #[schemars(description = "訊息是否為Markdown格式，預設為true")]
     #[serde(default = "default_is_markdown")]
     pub is_markdown: bool,
+    #[schemars(description = "等待使用者回應的逾時秒數（可選，未提供時使用全域設定，0 表示不逾時）")]
+    #[serde(default)]
+    pub timeout_seconds: Option<u64>,
+    #[schemars(description = "逾時時自動選擇的預定義選項 id 或文字（可選，未提供時回覆繼續提示詞）")]
+    #[serde(default)]
+    pub default_option: Option<String>,
+    #[schemars(description = "一次詢問多個獨立問題（可選），每個問題有自己的訊息与選項，在同一個視窗中回答；message 作為整體說明")]
+    #[serde(default)]
+    pub questions: Vec<QuestionInput>,
+    #[schemars(description = "要審查的 unified diff（可選），使用者可逐區塊核准、拒絕或留下意見")]
+    #[serde(default)]
+    pub diff: Option<String>,
+    #[schemars(description = "要審查的檔案 patch 列表（可選），與 diff 可擇一或同時提供")]
+    #[serde(default)]
+    pub patches: Vec<FilePatchInput>,
+    #[schemars(description = "顯示在訊息旁的附件（可選），例如圖表或截圖；圖片或 UTF-8 文字檔，每個以 base64 的 data 或本機檔案 path 提供")]
+    #[serde(default)]
+    pub attachments: Vec<AttachmentInput>,
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct ZhiFormRequest {
+    #[schemars(description = "要顯示在表單上方的說明")]
+    pub message: String,
+    #[schemars(description = "表單的 JSON Schema：type 為 object，properties 可為 string、number、integer、boolean（可帶 enum、enumNames、default、minimum、maximum、minLength、maxLength、pattern）或元素為基本型別的 array，required 列出必填欄位")]
+    pub schema: serde_json::Value,
+    #[schemars(description = "說明是否為Markdown格式，預設為true")]
+    #[serde(default = "default_is_markdown")]
+    pub is_markdown: bool,
+    #[schemars(description = "等待使用者填寫的逾時秒數（可選，未提供時使用全域設定，0 表示不逾時）；逾時時套用欄位預設值")]
+    #[serde(default)]
+    pub timeout_seconds: Option<u64>,
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct ZhiPollRequest {
+    #[schemars(description = "zhi_ask 傳回的票證 ID")]
+    pub ticket_id: String,
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct ZhiWaitRequest {
+    #[schemars(description = "zhi_ask 傳回的票證 ID")]
+    pub ticket_id: String,
+    #[schemars(description = "最多等待的秒數（可選，預設 60，0 表示等到使用者回覆為止）；時間到仍未回覆時傳回 pending 狀態")]
+    #[serde(default)]
+    pub timeout_seconds: Option<u64>,
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct ZhiNotifyRequest {
+    #[schemars(description = "狀態訊息，例如「正在執行資料庫遷移」")]
+    pub message: String,
+    #[schemars(description = "通知標題（可選）")]
+    #[serde(default)]
+    pub title: Option<String>,
+    #[schemars(description = "目前步驟（可選，從 1 開始），与 total 一起顯示為「步驟 3/7」")]
+    #[serde(default)]
+    pub step: Option<u32>,
+    #[schemars(description = "總步驟數（可選）")]
+    #[serde(default)]
+    pub total: Option<u32>,
+    #[schemars(description = "通知等級：info、success、warning 或 error，預設為 info")]
+    #[serde(default)]
+    pub level: NotifyLevel,
+    #[schemars(description = "是否播放設定中的提示音（需已啟用音訊通知），預設為 false")]
+    #[serde(default)]
+    pub play_sound: bool,
+}
+
+/// 進度通知的等級
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum NotifyLevel {
+    #[default]
+    Info,
+    Success,
+    Warning,
+    Error,
+}
+
+/// `zhi_notify` 發出的一則進度通知
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Notification {
+    pub id: String,
+    /// 發出通知的 MCP 工作階段
+    pub session_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub step: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total: Option<u32>,
+    #[serde(default)]
+    pub level: NotifyLevel,
+    #[serde(default)]
+    pub play_sound: bool,
+    /// 發出通知的 MCP 客戶端名稱
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_name: Option<String>,
+    /// 通知所屬的專案名稱
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project_name: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl Notification {
+    /// 單行摘要，用於系統匣提示与日誌
+    pub fn summary(&self) -> String {
+        let step = match (self.step, self.total) {
+            (Some(step), Some(total)) => format!("步驟 {}/{}：", step, total),
+            (Some(step), None) => format!("步驟 {}：", step),
+            _ => String::new(),
+        };
+        match &self.title {
+            Some(title) => format!("{}{} - {}", step, title, self.message),
+            None => format!("{}{}", step, self.message),
+        }
+    }
+}
+
+/// 一個工作階段的通知日誌
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationSession {
+    pub session_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project_name: Option<String>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+    /// 由舊到新排列，只保留最近的通知
+    pub notifications: Vec<Notification>,
+}
+
+/// `zhi` 請求中的單一附件
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct AttachmentInput {
+    #[schemars(description = "顯示的名稱（可選，預設為檔名）")]
+    #[serde(default)]
+    pub name: Option<String>,
+    #[schemars(description = "媒體類型，例如 image/png、text/markdown（可選，預設依副檔名或內容判斷）")]
+    #[serde(default)]
+    pub media_type: Option<String>,
+    #[schemars(description = "base64 編碼的內容，可帶 data:<媒體類型>;base64, 前綴；與 path 擇一")]
+    #[serde(default)]
+    pub data: Option<String>,
+    #[schemars(description = "本機檔案路徑，相對路徑以專案目錄為起點；與 data 擇一")]
+    #[serde(default)]
+    pub path: Option<String>,
+}
+
+/// `zhi` 請求中的單一檔案 patch
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct FilePatchInput {
+    #[schemars(description = "檔案路徑")]
+    pub path: String,
+    #[schemars(description = "此檔案的 unified diff，可只包含 @@ 區塊")]
+    pub diff: String,
+}
+
+/// `zhi` 請求中的單一問題
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct QuestionInput {
+    #[schemars(description = "問題 ID，回應中以此識別答案（可選，預設為 q1、q2…）")]
+    #[serde(default)]
+    pub id: Option<String>,
+    #[schemars(description = "問題內容")]
+    pub message: String,
+    #[schemars(description = "此問題的預定義選項（可選），格式与 predefined_options 相同")]
+    #[serde(default)]
+    pub predefined_options: Vec<PredefinedOptionInput>,
+    #[schemars(description = "此問題的選擇模式：single 或 multiple，預設為 multiple")]
+    #[serde(default)]
+    pub selection_mode: SelectionMode,
 }
 
 fn default_is_markdown() -> bool {
     true
 }
 
+/// `zhi` 請求中的預定義選項，純文字与物件兩種寫法皆可
+#[derive(Debug, Clone, Deserialize, schemars::JsonSchema)]
+#[serde(untagged)]
+pub enum PredefinedOptionInput {
+    Text(String),
+    Detailed {
+        #[schemars(description = "選項 ID，回應中以此識別選擇（可選，預設與 label 相同）")]
+        #[serde(default)]
+        id: Option<String>,
+        #[schemars(description = "顯示給使用者的選項文字")]
+        label: String,
+        #[schemars(description = "選項的補充說明（可選）")]
+        #[serde(default)]
+        description: Option<String>,
+        #[schemars(description = "是否為建議選項")]
+        #[serde(default)]
+        recommended: bool,
+        #[schemars(description = "是否為互斥選項，選擇時會取消其他選擇")]
+        #[serde(default)]
+        exclusive: bool,
+    },
+}
+
+/// 正規化後的預定義選項，純文字選項的 id 与 label 相同
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "PredefinedOptionInput")]
+pub struct PredefinedOption {
+    pub id: String,
+    pub label: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub recommended: bool,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub exclusive: bool,
+}
+
+impl From<PredefinedOptionInput> for PredefinedOption {
+    fn from(input: PredefinedOptionInput) -> Self {
+        match input {
+            PredefinedOptionInput::Text(label) => Self {
+                id: label.clone(),
+                label,
+                description: None,
+                recommended: false,
+                exclusive: false,
+            },
+            PredefinedOptionInput::Detailed { id, label, description, recommended, exclusive } => Self {
+                id: id.filter(|id| !id.trim().is_empty()).unwrap_or_else(|| label.clone()),
+                label,
+                description,
+                recommended,
+                exclusive,
+            },
+        }
+    }
+}
+
+/// 正規化後的問題，隨 `PopupRequest` 傳給弹窗
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PopupQuestion {
+    pub id: String,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub predefined_options: Vec<PredefinedOption>,
+    #[serde(default)]
+    pub selection_mode: SelectionMode,
+}
+
+/// 選項的選擇模式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SelectionMode {
+    /// 只能選擇一個選項
+    Single,
+    /// 可選擇多個選項
+    #[default]
+    Multiple,
+}
+
+/// `ji` 的請求，各操作需要的參數見 `JiyiAction::branch`
 #[derive(Debug, Deserialize, schemars::JsonSchema)]
 pub struct JiyiRequest {
-    #[schemars(description = "操作類型：記憶(新增記憶), 回憶(獲取專案訊息)")]
-    pub action: String,
-    #[schemars(description = "專案路徑（必需）")]
+    #[schemars(description = "操作類型，中英文名稱皆可；各操作的用途与必需參數見 Schema 中依 action 區分的分支")]
+    pub action: JiyiAction,
+    #[schemars(description = "專案路徑（scope 為 global 時可省略）")]
+    #[serde(default)]
     pub project_path: String,
-    #[schemars(description = "記憶內容（記憶操作時必需）")]
+    #[schemars(
+        description = "記憶範圍：global(使用者層級，所有專案共用), project(目前專案), both(兩者合併，專案記憶優先)；remember 預設為 project，其他操作預設為 both"
+    )]
+    #[serde(default)]
+    pub scope: Option<MemoryScope>,
+    #[schemars(description = "記憶 ID，可由 list 查看")]
+    #[serde(default)]
+    pub id: Option<String>,
+    #[schemars(description = "記憶內容")]
     #[serde(default)]
     pub content: String,
     #[schemars(
         description = "記憶分類：rule(規範規則), preference(使用者偏好), pattern(最佳實務), context(專案上下文)"
     )]
-    #[serde(default = "default_category")]
-    pub category: String,
+    #[serde(default)]
+    pub category: Option<String>,
+    #[schemars(description = "檢索內容")]
+    #[serde(default)]
+    pub query: Option<String>,
+    #[schemars(description = "最多傳回的記憶數量")]
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+impl JiyiRequest {
+    /// `ji` 的輸入 Schema：欄位由類型推導，再依 `action` 為每種操作加上說明与必需參數
+    ///
+    /// 分支以 if/then/else 串接而不是頂層 `oneOf`，部分客戶端（如 Anthropic API）不接受頂層含 oneOf/anyOf/allOf 的工具 Schema
+    pub fn input_schema() -> Arc<JsonObject> {
+        let mut actions: Vec<JiyiAction> = JiyiAction::NAMES.iter().map(|(_, action)| *action).collect();
+        actions.dedup();
+
+        // 由最後一個分支往前串接，未符合任何分支的 action 已被 enum 擋下
+        let mut branches: Option<serde_json::Value> = None;
+        for action in actions.into_iter().rev() {
+            let names: Vec<&str> = JiyiAction::NAMES
+                .iter()
+                .filter(|(_, candidate)| *candidate == action)
+                .map(|(name, _)| *name)
+                .collect();
+            let (description, required) = action.branch();
+            let mut branch = serde_json::json!({
+                "if": {
+                    "properties": { "action": { "enum": names } },
+                    "required": ["action"]
+                },
+                "then": {
+                    "description": description,
+                    "required": required
+                }
+            });
+            if let Some(rest) = branches {
+                branch["else"] = rest;
+            }
+            branches = Some(branch);
+        }
+
+        let mut schema = schema_object_for::<JiyiRequest>();
+        if let Some(serde_json::Value::Object(branches)) = branches {
+            Arc::make_mut(&mut schema).extend(branches);
+        }
+        schema
+    }
+}
+
+/// `ji` 的記憶範圍
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum MemoryScope {
+    /// 使用者層級的全域記憶
+    Global,
+    /// 目前專案的記憶
+    Project,
+    /// 全域与專案記憶合併
+    Both,
+}
+
+impl MemoryScope {
+    /// 顯示用的範圍名稱
+    pub fn label(&self) -> &'static str {
+        match self {
+            MemoryScope::Global => "全域",
+            MemoryScope::Project => "專案",
+            MemoryScope::Both => "全域与專案",
+        }
+    }
 }
 
-fn default_category() -> String {
-    "context".to_string()
+/// `ji` 的操作類型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum JiyiAction {
+    /// 新增記憶
+    Remember,
+    /// 獲取專案記憶總覽
+    Recall,
+    List,
+    Get,
+    Update,
+    Delete,
+    MoveCategory,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+impl JiyiAction {
+    /// 接受的操作名稱（含中英文別名）
+    pub const NAMES: [(&'static str, JiyiAction); 14] = [
+        ("記憶", JiyiAction::Remember),
+        ("记忆", JiyiAction::Remember),
+        ("remember", JiyiAction::Remember),
+        ("add", JiyiAction::Remember),
+        ("回憶", JiyiAction::Recall),
+        ("回忆", JiyiAction::Recall),
+        ("recall", JiyiAction::Recall),
+        ("list", JiyiAction::List),
+        ("get", JiyiAction::Get),
+        ("update", JiyiAction::Update),
+        ("delete", JiyiAction::Delete),
+        ("remove", JiyiAction::Delete),
+        ("move-category", JiyiAction::MoveCategory),
+        ("move", JiyiAction::MoveCategory),
+    ];
+
+    /// 操作的說明与除 `action` 外的必需參數
+    fn branch(self) -> (&'static str, &'static [&'static str]) {
+        match self {
+            JiyiAction::Remember => (
+                "新增記憶，category 預設為 context；同分類已有相近的記憶時不重複新增並傳回其 ID",
+                &["content"],
+            ),
+            JiyiAction::Recall => (
+                "獲取記憶總覽；提供 query 時依相關度只傳回最符合的記憶，可搭配 category 篩選、limit 限制數量（預設 8，最多 50）",
+                &[],
+            ),
+            JiyiAction::List => ("列出記憶及其 ID，可用 category 篩選", &[]),
+            JiyiAction::Get => ("依 ID 查看記憶", &["id"]),
+            JiyiAction::Update => ("依 ID 修改記憶內容，用於修正過時的記憶，而不是新增互相矛盾的條目", &["id", "content"]),
+            JiyiAction::Delete => ("依 ID 刪除記憶", &["id"]),
+            JiyiAction::MoveCategory => ("依 ID 將記憶移到 category 指定的分類", &["id", "category"]),
+        }
+    }
+}
+
+impl TryFrom<String> for JiyiAction {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let name = value.trim();
+        Self::NAMES
+            .iter()
+            .find(|(candidate, _)| candidate.eq_ignore_ascii_case(name))
+            .map(|(_, action)| *action)
+            .ok_or_else(|| {
+                let names: Vec<&str> = Self::NAMES.iter().map(|(name, _)| *name).collect();
+                format!("未知的操作類型: {}（支援：{}）", value, names.join("、"))
+            })
+    }
+}
+
+impl schemars::JsonSchema for JiyiAction {
+    fn schema_name() -> String {
+        "JiyiAction".to_string()
+    }
+
+    /// 列出所有接受的名稱，讓客戶端的 Schema 驗證也接受別名
+    fn json_schema(_: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+        schemars::schema::SchemaObject {
+            instance_type: Some(schemars::schema::InstanceType::String.into()),
+            enum_values: Some(Self::NAMES.iter().map(|(name, _)| serde_json::Value::from(*name)).collect()),
+            ..Default::default()
+        }
+        .into()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PopupRequest {
     pub id: String,
     pub message: String,
-    pub predefined_options: Option<Vec<String>>,
+    pub predefined_options: Option<Vec<PredefinedOption>>,
+    /// 選擇模式，弹窗據此限制可選數量
+    #[serde(default)]
+    pub selection_mode: SelectionMode,
     pub is_markdown: bool,
+    /// 逾時秒數，弹窗據此顯示倒數
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_seconds: Option<u64>,
+    /// 逾時時自動選擇的選項 id
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_option: Option<String>,
+    /// 發出請求的 MCP 客戶端名稱（clientInfo 的 title 或 name）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_name: Option<String>,
+    /// 發出請求的 MCP 客戶端版本
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_version: Option<String>,
+    /// 請求所屬的專案名稱
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project_name: Option<String>,
+    /// 請求所屬的專案路徑（MCP root 或工作目錄）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project_path: Option<String>,
+    /// 專案目前的 git 分支
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git_branch: Option<String>,
+    /// 需要在同一個視窗中逐一回答的問題
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub questions: Vec<PopupQuestion>,
+    /// 需要逐區塊審查的差異
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub diff_files: Vec<DiffFile>,
+    /// `zhi_form` 的表單欄位
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub form_fields: Vec<FormField>,
+    /// AI 提供的圖片与文字附件
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<PopupAttachment>,
 }
 
 /// 新的结构化回應資料格式
-#[derive(Debug, Deserialize)]
+///
+/// 同時作為 `zhi` 工具的輸出 Schema 與 `structuredContent`；
+/// 以 `McpResponse { ..., ..McpResponse::new(request_id, source) }` 只填入需要的欄位
+#[derive(Debug, Default, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct McpResponse {
+    #[schemars(description = "使用者輸入的文字")]
     pub user_input: Option<String>,
+    #[schemars(description = "使用者選擇的預定義選項文字")]
     pub selected_options: Vec<String>,
+    #[schemars(description = "使用者選擇的預定義選項 ID，與 selected_options 順序一致")]
+    #[serde(default)]
+    pub selected_option_ids: Vec<String>,
+    #[schemars(description = "使用者附加的圖片")]
     pub images: Vec<ImageAttachment>,
+    #[schemars(description = "各問題的答案，順序与請求的 questions 一致")]
+    #[serde(default)]
+    pub answers: Vec<QuestionAnswer>,
+    #[schemars(description = "差異審查結果，依檔案列出各區塊的判定与意見")]
+    #[serde(default)]
+    pub review: Vec<FileReview>,
+    #[schemars(description = "表單填寫的值，只有 zhi_form 使用")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub form_values: Option<serde_json::Value>,
+    #[schemars(description = "回應元資料")]
     pub metadata: ResponseMetadata,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+impl McpResponse {
+    /// 建立只含元資料的回應，時間為目前時間
+    pub fn new(request_id: Option<String>, source: &str) -> Self {
+        Self {
+            metadata: ResponseMetadata {
+                timestamp: Some(chrono::Utc::now().to_rfc3339()),
+                request_id,
+                source: Some(source.to_string()),
+                timed_out: false,
+            },
+            ..Default::default()
+        }
+    }
+
+    /// 填入選擇的選項，文字与 ID 的順序一致
+    pub fn with_selected_options(mut self, selected: Vec<PredefinedOption>) -> Self {
+        (self.selected_option_ids, self.selected_options) = split_selected(selected);
+        self
+    }
+}
+
+/// 序列化為傳給 MCP 服务器的 JSON 字串
+impl std::fmt::Display for McpResponse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let json = serde_json::to_string(self).map_err(|_| std::fmt::Error)?;
+        f.write_str(&json)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct QuestionAnswer {
+    #[schemars(description = "對應的問題 ID")]
+    pub question_id: String,
+    #[schemars(description = "使用者選擇的選項文字")]
+    #[serde(default)]
+    pub selected_options: Vec<String>,
+    #[schemars(description = "使用者選擇的選項 ID")]
+    #[serde(default)]
+    pub selected_option_ids: Vec<String>,
+    #[schemars(description = "使用者輸入的文字")]
+    #[serde(default)]
+    pub user_input: Option<String>,
+}
+
+impl QuestionAnswer {
+    /// 依選擇的選項建立答案
+    pub fn new(question_id: &str, selected: Vec<PredefinedOption>, user_input: Option<String>) -> Self {
+        let (selected_option_ids, selected_options) = split_selected(selected);
+        Self {
+            question_id: question_id.to_string(),
+            selected_options,
+            selected_option_ids,
+            user_input,
+        }
+    }
+}
+
+/// 將選擇的選項拆成 ID 与文字兩個順序一致的列表
+fn split_selected(selected: Vec<PredefinedOption>) -> (Vec<String>, Vec<String>) {
+    selected.into_iter().map(|option| (option.id, option.label)).unzip()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct FileReview {
+    #[schemars(description = "檔案路徑")]
+    pub path: String,
+    #[schemars(description = "對整個檔案的意見")]
+    #[serde(default)]
+    pub comment: Option<String>,
+    #[schemars(description = "各區塊的審查結果")]
+    pub hunks: Vec<HunkReview>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct HunkReview {
+    #[schemars(description = "區塊在檔案中的序號，從 1 開始")]
+    pub index: usize,
+    #[schemars(description = "區塊標頭，例如 @@ -1,3 +1,4 @@")]
+    pub header: String,
+    #[schemars(description = "審查判定")]
+    pub verdict: ReviewVerdict,
+    #[schemars(description = "對此區塊的意見")]
+    #[serde(default)]
+    pub comment: Option<String>,
+}
+
+/// 區塊的審查判定
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ReviewVerdict {
+    /// 核准
+    Approved,
+    /// 拒絕，需要修改
+    Rejected,
+    /// 只留下意見
+    Commented,
+    /// 未審查
+    #[default]
+    Pending,
+}
+
+impl FileReview {
+    /// 依區塊判定与意見建立檔案審查結果
+    pub fn new(file: &DiffFile, comment: Option<String>, verdicts: Vec<(ReviewVerdict, Option<String>)>) -> Self {
+        let hunks = file
+            .hunks
+            .iter()
+            .zip(verdicts)
+            .enumerate()
+            .map(|(index, (hunk, (verdict, comment)))| HunkReview {
+                index: index + 1,
+                header: hunk.header.clone(),
+                // 未判定但有意見時視為意見
+                verdict: match (verdict, &comment) {
+                    (ReviewVerdict::Pending, Some(_)) => ReviewVerdict::Commented,
+                    (verdict, _) => verdict,
+                },
+                comment,
+            })
+            .collect();
+        Self {
+            path: file.path.clone(),
+            comment,
+            hunks,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct ImageAttachment {
+    #[schemars(description = "Base64 編碼的圖片資料")]
     pub data: String,
+    #[schemars(description = "圖片 MIME 類型，例如 image/png")]
     pub media_type: String,
+    #[schemars(description = "原始檔案名稱")]
     pub filename: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Default, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct ResponseMetadata {
+    #[schemars(description = "回應時間（RFC 3339）")]
     pub timestamp: Option<String>,
+    #[schemars(description = "對應的請求 ID")]
     pub request_id: Option<String>,
+    #[schemars(description = "回應來源，例如 popup、popup_enhance、cancelled、timeout")]
     pub source: Option<String>,
+    #[schemars(description = "是否因逾時而自動回覆")]
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub timed_out: bool,
 }
 
 /// 舊格式相容性支援
@@ -85,38 +701,13 @@ pub struct ImageSource {
     pub data: String,
 }
 
-/// 統一的回應建構函數
-///
-/// 用於生成標準的JSON回應格式，確保無GUI和有GUI模式輸出一致
-pub fn build_mcp_response(
-    user_input: Option<String>,
-    selected_options: Vec<String>,
-    images: Vec<ImageAttachment>,
-    request_id: Option<String>,
-    source: &str,
-) -> serde_json::Value {
-    serde_json::json!({
-        "user_input": user_input,
-        "selected_options": selected_options,
-        "images": images,
-        "metadata": {
-            "timestamp": chrono::Utc::now().to_rfc3339(),
-            "request_id": request_id,
-            "source": source
-        }
-    })
-}
-
-/// 建構傳送操作的回應
-pub fn build_send_response(
-    user_input: Option<String>,
-    selected_options: Vec<String>,
-    images: Vec<ImageAttachment>,
-    request_id: Option<String>,
-    source: &str,
-) -> String {
-    let response = build_mcp_response(user_input, selected_options, images, request_id, source);
-    response.to_string()
+/// 建構表單送出的回應
+pub fn build_form_response(values: serde_json::Value, request_id: Option<String>, source: &str) -> String {
+    McpResponse {
+        form_values: Some(values),
+        ..McpResponse::new(request_id, source)
+    }
+    .to_string()
 }
 
 /// 建構繼續操作的回應
@@ -128,6 +719,32 @@ pub fn build_continue_response(request_id: Option<String>, source: &str) -> Stri
         "請按照最佳實務繼續".to_string()
     };
 
-    let response = build_mcp_response(Some(continue_prompt), vec![], vec![], request_id, source);
-    response.to_string()
+    McpResponse {
+        user_input: Some(continue_prompt),
+        ..McpResponse::new(request_id, source)
+    }
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_jiyi_input_schema_branches() {
+        let schema = JiyiRequest::input_schema();
+        assert_eq!(schema["required"], serde_json::json!(["action"]));
+
+        // 沿著 else 找出每種操作分支的必需參數
+        let mut required = Vec::new();
+        let mut branch = serde_json::Value::Object((*schema).clone());
+        while let Some(names) = branch["if"]["properties"]["action"]["enum"].as_array() {
+            required.push((names[0].clone(), branch["then"]["required"].clone()));
+            branch = branch["else"].clone();
+        }
+        assert_eq!(required.len(), 7);
+        assert!(required.contains(&("update".into(), serde_json::json!(["id", "content"]))));
+        assert!(required.contains(&("記憶".into(), serde_json::json!(["content"]))));
+        assert!(required.contains(&("回憶".into(), serde_json::json!([]))));
+    }
 }
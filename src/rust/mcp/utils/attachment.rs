@@ -0,0 +1,260 @@
+//! AI 提供的附件
+//!
+//! `zhi` 可附上圖片或文字檔，以 base64 直接傳入或由服务器讀取本機檔案。
+//! 本機檔案先正規化路徑，只接受位於專案目錄或客戶端 roots 之內、大小在上限內的一般檔案
+
+use anyhow::Result;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crate::constants::mcp::{ATTACHMENT_MAX_BYTES, ATTACHMENT_MAX_COUNT};
+use crate::mcp::types::AttachmentInput;
+
+/// 附件種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AttachmentKind {
+    Image,
+    Text,
+}
+
+/// 弹窗中顯示的附件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PopupAttachment {
+    pub name: String,
+    pub media_type: String,
+    pub kind: AttachmentKind,
+    /// 圖片為 base64，文字為檔案內容
+    pub data: String,
+    /// 原始內容的位元組數
+    pub size: usize,
+    /// 讀取的本機檔案（正規化後的路徑）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+}
+
+impl PopupAttachment {
+    /// 單行說明，用於無法顯示圖片的終端与 elicitation
+    pub fn summary(&self) -> String {
+        let location = self.path.as_deref().map(|path| format!("，{}", path)).unwrap_or_default();
+        format!("{}（{}，{} 位元組{}）", self.name, self.media_type, self.size, location)
+    }
+}
+
+/// 讀取並檢查附件
+///
+/// 本機檔案必須位於 `roots` 之一（專案目錄与客戶端 roots），相對路徑以第一個目錄為起點
+pub fn resolve_attachments(inputs: Vec<AttachmentInput>, roots: &[PathBuf]) -> Result<Vec<PopupAttachment>> {
+    if inputs.len() > ATTACHMENT_MAX_COUNT {
+        anyhow::bail!("附件最多 {} 個，收到 {} 個", ATTACHMENT_MAX_COUNT, inputs.len());
+    }
+
+    inputs
+        .into_iter()
+        .enumerate()
+        .map(|(index, input)| {
+            resolve_attachment(input, roots).map_err(|e| anyhow::anyhow!("附件 {}：{}", index + 1, e))
+        })
+        .collect()
+}
+
+fn resolve_attachment(input: AttachmentInput, roots: &[PathBuf]) -> Result<PopupAttachment> {
+    let (bytes, path, declared_type) = match (input.data, input.path) {
+        (Some(data), None) => {
+            // 接受 `data:<媒體類型>;base64,` 前綴，前綴中的媒體類型在未另外指定時使用
+            let (declared_type, data) = match data.split_once(";base64,") {
+                Some((prefix, data)) => (prefix.strip_prefix("data:").map(str::to_string), data),
+                None => (None, data.as_str()),
+            };
+            // 解碼前先以編碼長度估算大小，避免解碼過大的內容
+            if data.len() / 4 * 3 > ATTACHMENT_MAX_BYTES + 2 {
+                anyhow::bail!("內容超過 {} 位元組上限", ATTACHMENT_MAX_BYTES);
+            }
+            let bytes = BASE64
+                .decode(data.trim())
+                .map_err(|e| anyhow::anyhow!("data 不是有效的 base64: {}", e))?;
+            (bytes, None, declared_type)
+        }
+        (None, Some(path)) => {
+            let (bytes, path) = read_attachment_file(&path, roots)?;
+            (bytes, Some(path), None)
+        }
+        (Some(_), Some(_)) => anyhow::bail!("data 与 path 只能擇一"),
+        (None, None) => anyhow::bail!("需要提供 data 或 path"),
+    };
+    if bytes.len() > ATTACHMENT_MAX_BYTES {
+        anyhow::bail!("內容超過 {} 位元組上限", ATTACHMENT_MAX_BYTES);
+    }
+
+    let name = input
+        .name
+        .filter(|name| !name.trim().is_empty())
+        .or_else(|| {
+            path.as_deref()
+                .and_then(|path| Path::new(path).file_name())
+                .map(|name| name.to_string_lossy().to_string())
+        })
+        .unwrap_or_else(|| "附件".to_string());
+    let media_type = input
+        .media_type
+        .or(declared_type)
+        .or_else(|| media_type_from_name(&name).map(str::to_string))
+        .or_else(|| sniff_image_type(&bytes).map(str::to_string));
+
+    let size = bytes.len();
+    match media_type {
+        Some(media_type) if media_type.starts_with("image/") => Ok(PopupAttachment {
+            name,
+            media_type,
+            kind: AttachmentKind::Image,
+            data: BASE64.encode(&bytes),
+            size,
+            path,
+        }),
+        media_type => {
+            let text = String::from_utf8(bytes)
+                .map_err(|_| anyhow::anyhow!("{} 不是圖片，也不是 UTF-8 文字", name))?;
+            Ok(PopupAttachment {
+                name,
+                media_type: media_type.unwrap_or_else(|| "text/plain".to_string()),
+                kind: AttachmentKind::Text,
+                data: text,
+                size,
+                path,
+            })
+        }
+    }
+}
+
+/// 讀取本機檔案
+///
+/// 正規化路徑（解析符號連結与 `..`）後必須位於某個 root 之內；開啟一次檔案，
+/// 由同一個檔案控制代碼檢查類型並限量讀取，避免檢查後檔案被替換
+fn read_attachment_file(path: &str, roots: &[PathBuf]) -> Result<(Vec<u8>, String)> {
+    let path = Path::new(path);
+    let path = match roots.first() {
+        Some(base_dir) if path.is_relative() => base_dir.join(path),
+        _ => path.to_path_buf(),
+    };
+    let canonical = fs::canonicalize(&path).map_err(|e| anyhow::anyhow!("無法存取 {}: {}", path.display(), e))?;
+    let contained = roots
+        .iter()
+        .filter_map(|root| fs::canonicalize(root).ok())
+        .any(|root| canonical.starts_with(root));
+    if !contained {
+        anyhow::bail!("{} 不在專案目錄或客戶端 roots 之內", canonical.display());
+    }
+
+    let file = File::open(&canonical).map_err(|e| anyhow::anyhow!("無法開啟 {}: {}", canonical.display(), e))?;
+    if !file.metadata()?.is_file() {
+        anyhow::bail!("{} 不是一般檔案", canonical.display());
+    }
+    let mut bytes = Vec::new();
+    file.take(ATTACHMENT_MAX_BYTES as u64 + 1).read_to_end(&mut bytes)?;
+    if bytes.len() > ATTACHMENT_MAX_BYTES {
+        anyhow::bail!("{} 超過 {} 位元組上限", canonical.display(), ATTACHMENT_MAX_BYTES);
+    }
+    Ok((bytes, canonical.to_string_lossy().to_string()))
+}
+
+/// 依副檔名判斷媒體類型
+fn media_type_from_name(name: &str) -> Option<&'static str> {
+    let extension = Path::new(name).extension()?.to_string_lossy().to_ascii_lowercase();
+    let media_type = match extension.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "bmp" => "image/bmp",
+        "svg" => "image/svg+xml",
+        "md" | "markdown" => "text/markdown",
+        "json" => "application/json",
+        "csv" => "text/csv",
+        "html" | "htm" => "text/html",
+        _ => return None,
+    };
+    Some(media_type)
+}
+
+/// 依檔頭判斷常見的圖片格式
+fn sniff_image_type(bytes: &[u8]) -> Option<&'static str> {
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("image/png")
+    } else if bytes.starts_with(&[0xff, 0xd8, 0xff]) {
+        Some("image/jpeg")
+    } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+        Some("image/gif")
+    } else if bytes.len() >= 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        Some("image/webp")
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(data: Option<&str>, path: Option<&str>) -> AttachmentInput {
+        AttachmentInput {
+            name: None,
+            media_type: None,
+            data: data.map(str::to_string),
+            path: path.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_resolve_base64_attachments() {
+        // 只有 PNG 檔頭，未提供媒體類型時由檔頭判斷
+        let png = BASE64.encode(b"\x89PNG\r\n\x1a\n0000");
+        let text = format!("data:text/plain;base64,{}", BASE64.encode("hello"));
+        let attachments = resolve_attachments(vec![input(Some(&png), None), input(Some(&text), None)], &[]).unwrap();
+
+        assert_eq!(attachments[0].kind, AttachmentKind::Image);
+        assert_eq!(attachments[0].media_type, "image/png");
+        assert_eq!(attachments[1].kind, AttachmentKind::Text);
+        assert_eq!(attachments[1].media_type, "text/plain");
+        assert_eq!(attachments[1].data, "hello");
+
+        let binary = BASE64.encode([0xff, 0xfe, 0x00]);
+        assert!(resolve_attachments(vec![input(Some(&binary), None)], &[]).is_err());
+        assert!(resolve_attachments(vec![input(Some(&png), Some("a.png"))], &[]).is_err());
+    }
+
+    #[test]
+    fn test_resolve_file_attachments() {
+        let base = std::env::temp_dir().join(format!("cunzhi-attachment-{}", std::process::id()));
+        let dir = base.join("project");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("notes.md"), "# 標題").unwrap();
+        fs::write(base.join("secret.txt"), "secret").unwrap();
+        let roots = [dir.clone()];
+
+        let attachments = resolve_attachments(vec![input(None, Some("notes.md"))], &roots).unwrap();
+        assert_eq!(attachments[0].name, "notes.md");
+        assert_eq!(attachments[0].media_type, "text/markdown");
+        assert_eq!(attachments[0].data, "# 標題");
+
+        // 目錄与不存在的檔案都不接受
+        assert!(resolve_attachments(vec![input(None, Some("."))], &roots).is_err());
+        assert!(resolve_attachments(vec![input(None, Some("missing.txt"))], &roots).is_err());
+
+        // 專案目錄之外的檔案，不論以 `..`、絕對路徑或符號連結指向，都不接受
+        let outside = base.join("secret.txt").to_string_lossy().to_string();
+        assert!(resolve_attachments(vec![input(None, Some("../secret.txt"))], &roots).is_err());
+        assert!(resolve_attachments(vec![input(None, Some(&outside))], &roots).is_err());
+        assert!(resolve_attachments(vec![input(None, Some("notes.md"))], &[]).is_err());
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(base.join("secret.txt"), dir.join("link.txt")).unwrap();
+            assert!(resolve_attachments(vec![input(None, Some("link.txt"))], &roots).is_err());
+        }
+
+        fs::remove_dir_all(&base).unwrap();
+    }
+}
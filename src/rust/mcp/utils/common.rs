@@ -124,6 +124,9 @@ pub fn generate_request_id() -> String {
     uuid::Uuid::new_v4().to_string()
 }
 
-
-
-
+/// 讀取檔案修改時間
+///
+/// 用於輪詢偵測檔案變更，檔案不存在或無法讀取時傳回 None
+pub fn file_modified_time(path: &Path) -> Option<std::time::SystemTime> {
+    std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
@@ -0,0 +1,118 @@
+//! 請求來源資訊
+//!
+//! 收集發出請求的 MCP 客戶端与專案（roots、工作目錄、git 分支），讓弹窗標示是哪個助手在詢問
+
+use rmcp::{Peer, RoleServer};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use super::common::decode_and_normalize_path;
+use crate::constants::mcp::ROOTS_REQUEST_TIMEOUT_MS;
+use crate::log_debug;
+
+/// 發出請求的客戶端与專案
+#[derive(Debug, Default)]
+pub struct RequestOrigin {
+    pub client_name: Option<String>,
+    pub client_version: Option<String>,
+    pub project_name: Option<String>,
+    pub project_path: Option<String>,
+    pub git_branch: Option<String>,
+    /// 客戶端回報的所有本機 root，未回報時為空
+    pub roots: Vec<PathBuf>,
+}
+
+/// 解析請求來源
+///
+/// 客戶端名稱取自 `initialize` 的 clientInfo；專案优先使用客戶端回報的第一個 `file://` root，
+/// 客戶端不支援 roots 或查詢失敗時使用 MCP 服务器的工作目錄
+pub async fn resolve_request_origin(peer: &Peer<RoleServer>) -> RequestOrigin {
+    let mut origin = RequestOrigin::default();
+
+    if let Some(info) = peer.peer_info() {
+        let client = &info.client_info;
+        origin.client_name = Some(client.title.clone().unwrap_or_else(|| client.name.clone()));
+        origin.client_version = Some(client.version.clone()).filter(|version| !version.is_empty());
+    }
+
+    let roots = local_roots(peer).await;
+    let (project_dir, root_name) = match roots.first() {
+        Some((path, name)) => (Some(path.clone()), name.clone()),
+        None => (std::env::current_dir().ok(), None),
+    };
+    origin.roots = roots.into_iter().map(|(path, _)| path).collect();
+
+    if let Some(dir) = project_dir {
+        origin.project_name = root_name.or_else(|| {
+            dir.file_name().map(|name| name.to_string_lossy().to_string())
+        });
+        origin.git_branch = git_branch(&dir);
+        origin.project_path = Some(dir.to_string_lossy().to_string());
+    }
+
+    origin
+}
+
+/// 查詢客戶端的本機 root（`file://`），傳回路徑与名稱
+async fn local_roots(peer: &Peer<RoleServer>) -> Vec<(PathBuf, Option<String>)> {
+    let supports_roots = peer
+        .peer_info()
+        .is_some_and(|info| info.capabilities.roots.is_some());
+    if !supports_roots {
+        return Vec::new();
+    }
+
+    let result = tokio::time::timeout(
+        Duration::from_millis(ROOTS_REQUEST_TIMEOUT_MS),
+        peer.list_roots(),
+    )
+    .await;
+    let roots = match result {
+        Ok(Ok(result)) => result.roots,
+        Ok(Err(e)) => {
+            log_debug!("查詢 MCP roots 失敗，改用工作目錄: {}", e);
+            return Vec::new();
+        }
+        Err(_) => {
+            log_debug!("查詢 MCP roots 逾時，改用工作目錄");
+            return Vec::new();
+        }
+    };
+
+    roots
+        .into_iter()
+        .filter_map(|root| {
+            let path = root.uri.strip_prefix("file://")?;
+            let path = decode_and_normalize_path(path).ok()?;
+            Some((PathBuf::from(path), root.name))
+        })
+        .collect()
+}
+
+/// 讀取目錄所在 git 倉庫目前的分支，分離 HEAD 時傳回短 commit
+fn git_branch(dir: &Path) -> Option<String> {
+    let git_dir = find_git_dir(dir)?;
+    let head = std::fs::read_to_string(git_dir.join("HEAD")).ok()?;
+    let head = head.trim();
+
+    match head.strip_prefix("ref: ") {
+        Some(reference) => Some(reference.trim_start_matches("refs/heads/").to_string()),
+        None => Some(head.chars().take(7).collect()),
+    }
+}
+
+/// 向上查找 `.git`，支援 worktree 与子模組使用的 `gitdir:` 檔案
+fn find_git_dir(start: &Path) -> Option<PathBuf> {
+    for dir in start.ancestors() {
+        let git_path = dir.join(".git");
+        if git_path.is_dir() {
+            return Some(git_path);
+        }
+        if git_path.is_file() {
+            let content = std::fs::read_to_string(&git_path).ok()?;
+            let target = content.trim().strip_prefix("gitdir:")?.trim();
+            return Some(dir.join(target));
+        }
+    }
+    None
+}
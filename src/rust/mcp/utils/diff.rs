@@ -0,0 +1,215 @@
+//! Unified diff 解析
+//!
+//! 將 `git diff` 等工具產生的 unified diff 拆成檔案与區塊，供弹窗逐區塊審查
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+/// 差異中的單一檔案
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DiffFile {
+    /// 變更後的路徑，刪除檔案時為原路徑
+    pub path: String,
+    /// 重新命名前的路徑
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub old_path: Option<String>,
+    pub hunks: Vec<DiffHunk>,
+}
+
+/// 差異區塊
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DiffHunk {
+    /// `@@ -a,b +c,d @@` 標頭行
+    pub header: String,
+    /// 區塊內容，每行保留 ` `、`+`、`-` 前綴
+    pub lines: Vec<String>,
+}
+
+/// 區塊剩餘的行數，依標頭計算
+struct HunkState {
+    old_remaining: usize,
+    new_remaining: usize,
+}
+
+impl HunkState {
+    fn is_done(&self) -> bool {
+        self.old_remaining == 0 && self.new_remaining == 0
+    }
+}
+
+/// 解析 unified diff
+///
+/// `default_path` 用於沒有 `---`/`+++` 檔案標頭、只有區塊的差異（例如單一檔案的 patch）
+pub fn parse_unified_diff(text: &str, default_path: Option<&str>) -> Result<Vec<DiffFile>> {
+    let mut files: Vec<DiffFile> = Vec::new();
+    let mut hunk_state: Option<HunkState> = None;
+
+    for line in text.lines() {
+        // 區塊內容依標頭的行數判斷，避免把以 `---` 開頭的刪除行誤認為檔案標頭
+        // 行數与標頭不符時，遇到下一個區塊或檔案標頭即結束目前區塊
+        let in_hunk = hunk_state.as_ref().is_some_and(|state| !state.is_done())
+            && !line.starts_with("@@")
+            && !line.starts_with("diff --git ");
+        if let Some(state) = hunk_state.as_mut().filter(|_| in_hunk) {
+            // 部分工具會去掉空白上下文行的前綴
+            let content = if line.is_empty() { " " } else { line };
+            match content.chars().next() {
+                Some(' ') => {
+                    state.old_remaining = state.old_remaining.saturating_sub(1);
+                    state.new_remaining = state.new_remaining.saturating_sub(1);
+                }
+                Some('-') => state.old_remaining = state.old_remaining.saturating_sub(1),
+                Some('+') => state.new_remaining = state.new_remaining.saturating_sub(1),
+                // `\ No newline at end of file`
+                Some('\\') => {}
+                _ => anyhow::bail!("差異區塊內容格式錯誤: {}", line),
+            }
+            if let Some(hunk) = files.last_mut().and_then(|file| file.hunks.last_mut()) {
+                hunk.lines.push(content.to_string());
+            }
+            continue;
+        }
+
+        if line.starts_with('\\') {
+            if let Some(hunk) = files.last_mut().and_then(|file| file.hunks.last_mut()) {
+                hunk.lines.push(line.to_string());
+            }
+        } else if let Some(rest) = line.strip_prefix("diff --git ") {
+            let path = rest.rsplit_once(" b/").map_or(rest, |(_, path)| path);
+            files.push(DiffFile {
+                path: path.to_string(),
+                old_path: None,
+                hunks: Vec::new(),
+            });
+            hunk_state = None;
+        } else if let Some(path) = line.strip_prefix("--- ") {
+            // 沒有 `diff --git` 行或上一個檔案已有區塊時開始新檔案
+            if files.last().is_none_or(|file| !file.hunks.is_empty()) {
+                files.push(DiffFile {
+                    path: String::new(),
+                    old_path: None,
+                    hunks: Vec::new(),
+                });
+            }
+            if let (Some(file), Some(path)) = (files.last_mut(), header_path(path, "a/")) {
+                file.old_path = Some(path);
+            }
+            hunk_state = None;
+        } else if let Some(path) = line.strip_prefix("+++ ") {
+            if let Some(file) = files.last_mut() {
+                match header_path(path, "b/") {
+                    Some(path) => file.path = path,
+                    // 刪除的檔案使用原路徑
+                    None => file.path = file.old_path.clone().unwrap_or_default(),
+                }
+            }
+        } else if line.starts_with("@@") {
+            let (old_remaining, new_remaining) = parse_hunk_header(line)?;
+            if files.is_empty() {
+                let Some(path) = default_path else {
+                    anyhow::bail!("差異缺少檔案標頭（--- / +++）");
+                };
+                files.push(DiffFile {
+                    path: path.to_string(),
+                    old_path: None,
+                    hunks: Vec::new(),
+                });
+            }
+            if let Some(file) = files.last_mut() {
+                file.hunks.push(DiffHunk {
+                    header: line.to_string(),
+                    lines: Vec::new(),
+                });
+            }
+            hunk_state = Some(HunkState { old_remaining, new_remaining });
+        }
+        // 其他行（index、mode、rename 等）不影響審查，略過
+    }
+
+    for file in files.iter_mut() {
+        if file.path.is_empty() {
+            file.path = default_path.unwrap_or_default().to_string();
+        }
+        // 路徑未改變時不需要保留原路徑
+        if file.old_path.as_deref() == Some(file.path.as_str()) {
+            file.old_path = None;
+        }
+    }
+    files.retain(|file| !file.hunks.is_empty());
+
+    if files.is_empty() {
+        anyhow::bail!("差異中找不到任何區塊（@@）");
+    }
+    Ok(files)
+}
+
+/// 取出 `---`/`+++` 標頭中的路徑，`/dev/null` 傳回 None
+fn header_path(value: &str, prefix: &str) -> Option<String> {
+    // 標頭可能以 tab 附加時間戳記
+    let path = value.split('\t').next().unwrap_or(value).trim();
+    if path == "/dev/null" {
+        return None;
+    }
+    Some(path.strip_prefix(prefix).unwrap_or(path).to_string())
+}
+
+/// 解析 `@@ -a,b +c,d @@` 標頭，傳回原檔与新檔的行數
+fn parse_hunk_header(line: &str) -> Result<(usize, usize)> {
+    let invalid = || anyhow::anyhow!("差異區塊標頭格式錯誤: {}", line);
+    let mut ranges = line.trim_start_matches('@').split_whitespace();
+    let old = ranges.next().and_then(|range| range.strip_prefix('-')).ok_or_else(invalid)?;
+    let new = ranges.next().and_then(|range| range.strip_prefix('+')).ok_or_else(invalid)?;
+    Ok((range_len(old).ok_or_else(invalid)?, range_len(new).ok_or_else(invalid)?))
+}
+
+/// `start,count` 或 `start`（行數為 1）
+fn range_len(range: &str) -> Option<usize> {
+    match range.split_once(',') {
+        Some((start, count)) => {
+            start.parse::<usize>().ok()?;
+            count.parse().ok()
+        }
+        None => range.parse::<usize>().ok().map(|_| 1),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_git_diff() {
+        let diff = "diff --git a/src/lib.rs b/src/lib.rs\n\
+                    index 1234567..89abcde 100644\n\
+                    --- a/src/lib.rs\n\
+                    +++ b/src/lib.rs\n\
+                    @@ -1,3 +1,3 @@\n \
+                    fn main() {\n\
+                    --- removed\n\
+                    +++ added\n \
+                    }\n\
+                    @@ -10 +10,2 @@\n\
+                    -old\n\
+                    +new\n\
+                    +more\n";
+
+        let files = parse_unified_diff(diff, None).unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].path, "src/lib.rs");
+        assert_eq!(files[0].old_path, None);
+        assert_eq!(files[0].hunks.len(), 2);
+        // 以 `---` 開頭的刪除行屬於區塊內容
+        assert_eq!(files[0].hunks[0].lines, vec![" fn main() {", "--- removed", "+++ added", " }"]);
+        assert_eq!(files[0].hunks[1].lines.len(), 3);
+    }
+
+    #[test]
+    fn test_parse_hunks_without_file_header() {
+        let files = parse_unified_diff("@@ -1 +1 @@\n-a\n+b\n", Some("README.md")).unwrap();
+        assert_eq!(files[0].path, "README.md");
+        assert_eq!(files[0].hunks[0].lines, vec!["-a", "+b"]);
+
+        assert!(parse_unified_diff("@@ -1 +1 @@\n-a\n+b\n", None).is_err());
+        assert!(parse_unified_diff("no diff here", None).is_err());
+    }
+}
@@ -0,0 +1,417 @@
+//! 表單 Schema 解析与驗證
+//!
+//! 將 `zhi_form` 收到的 JSON Schema 轉為弹窗可直接渲染的欄位列表，並以同一份欄位定義驗證使用者送出的值
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// 欄位類型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FormFieldKind {
+    String,
+    Number,
+    Integer,
+    Boolean,
+    Array,
+}
+
+/// 列舉欄位的可選值
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FormOption {
+    pub value: Value,
+    /// 顯示文字，取自 `enumNames`，未提供時為值本身
+    pub label: String,
+}
+
+/// 表單欄位，對應 Schema `properties` 中的一個屬性
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FormField {
+    pub name: String,
+    /// 顯示名稱，未提供 `title` 時使用欄位名稱
+    pub title: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub kind: FormFieldKind,
+    /// 陣列元素類型，只有 array 欄位使用
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub item_kind: Option<FormFieldKind>,
+    #[serde(default)]
+    pub required: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<Value>,
+    /// 可選值，取自 `enum`；array 欄位取自 `items.enum`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub options: Vec<FormOption>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub minimum: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub maximum: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_length: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_length: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_items: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_items: Option<usize>,
+}
+
+/// 解析表單 Schema
+///
+/// 只支援一層 `object`，屬性可為 string、number、integer、boolean（可帶 `enum`）
+/// 或元素為基本型別的 array；欄位依 `properties` 的順序排列
+pub fn parse_form_schema(schema: &Value) -> Result<Vec<FormField>> {
+    let Some(schema) = schema.as_object() else {
+        anyhow::bail!("schema 必須是 JSON 物件");
+    };
+    if let Some(kind) = schema.get("type").filter(|kind| kind.as_str() != Some("object")) {
+        anyhow::bail!("schema 的 type 必須是 object，收到: {}", kind);
+    }
+    let Some(properties) = schema.get("properties").and_then(Value::as_object).filter(|p| !p.is_empty()) else {
+        anyhow::bail!("schema 缺少 properties 或沒有任何欄位");
+    };
+
+    let required: Vec<&str> = schema
+        .get("required")
+        .and_then(Value::as_array)
+        .map(|names| names.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+    if let Some(missing) = required.iter().find(|name| !properties.contains_key(**name)) {
+        anyhow::bail!("required 中的欄位 {} 不在 properties 中", missing);
+    }
+
+    let mut fields = Vec::with_capacity(properties.len());
+    for (name, property) in properties {
+        if name.trim().is_empty() {
+            anyhow::bail!("欄位名稱不能為空");
+        }
+        let mut field = parse_field(name, property).map_err(|e| anyhow::anyhow!("欄位 {}: {}", name, e))?;
+        field.required = required.contains(&name.as_str());
+        if let Some(default) = field.default.clone() {
+            field.default = Some(validate_value(&field, &default).map_err(|e| anyhow::anyhow!("欄位 {} 的預設值{}", name, e))?);
+        }
+        fields.push(field);
+    }
+    Ok(fields)
+}
+
+fn parse_field(name: &str, property: &Value) -> Result<FormField> {
+    let Some(property) = property.as_object() else {
+        anyhow::bail!("屬性定義必須是 JSON 物件");
+    };
+    let kind = parse_kind(property)?;
+
+    let item_kind = match kind {
+        FormFieldKind::Array => {
+            let items = property.get("items").and_then(Value::as_object);
+            let item_kind = match items {
+                Some(items) => parse_kind(items)?,
+                None => FormFieldKind::String,
+            };
+            if matches!(item_kind, FormFieldKind::Array | FormFieldKind::Boolean) {
+                anyhow::bail!("array 的元素只支援 string、number 或 integer");
+            }
+            Some(item_kind)
+        }
+        _ => None,
+    };
+    // array 欄位的可選值与字串限制定義在 items 中
+    let source = match kind {
+        FormFieldKind::Array => property.get("items").and_then(Value::as_object).unwrap_or(property),
+        _ => property,
+    };
+
+    let options = match source.get("enum") {
+        Some(Value::Array(values)) if !values.is_empty() => {
+            let names = source.get("enumNames").and_then(Value::as_array);
+            values
+                .iter()
+                .enumerate()
+                .map(|(index, value)| FormOption {
+                    value: value.clone(),
+                    label: names
+                        .and_then(|names| names.get(index))
+                        .and_then(Value::as_str)
+                        .map(str::to_string)
+                        .unwrap_or_else(|| display_value(value)),
+                })
+                .collect()
+        }
+        Some(_) => anyhow::bail!("enum 必須是非空陣列"),
+        None => Vec::new(),
+    };
+
+    let pattern = source.get("pattern").and_then(Value::as_str).map(str::to_string);
+    if let Some(pattern) = &pattern {
+        regex::Regex::new(pattern).map_err(|e| anyhow::anyhow!("pattern 無效: {}", e))?;
+    }
+
+    let field = FormField {
+        name: name.to_string(),
+        title: property
+            .get("title")
+            .and_then(Value::as_str)
+            .filter(|title| !title.trim().is_empty())
+            .unwrap_or(name)
+            .to_string(),
+        description: property.get("description").and_then(Value::as_str).map(str::to_string),
+        kind,
+        item_kind,
+        required: false,
+        default: property.get("default").filter(|value| !value.is_null()).cloned(),
+        options,
+        minimum: source.get("minimum").and_then(Value::as_f64),
+        maximum: source.get("maximum").and_then(Value::as_f64),
+        min_length: source.get("minLength").and_then(Value::as_u64).map(|n| n as usize),
+        max_length: source.get("maxLength").and_then(Value::as_u64).map(|n| n as usize),
+        pattern,
+        min_items: property.get("minItems").and_then(Value::as_u64).map(|n| n as usize),
+        max_items: property.get("maxItems").and_then(Value::as_u64).map(|n| n as usize),
+    };
+
+    // 可選值本身也必須符合欄位類型
+    let element_kind = field.item_kind.unwrap_or(field.kind);
+    if let Some(option) = field.options.iter().find(|option| check_kind(element_kind, &option.value).is_err()) {
+        anyhow::bail!("enum 的值 {} 不符合類型 {:?}", option.value, element_kind);
+    }
+    Ok(field)
+}
+
+/// 讀取 `type`，未指定時依 `enum` 的第一個值推斷
+fn parse_kind(property: &Map<String, Value>) -> Result<FormFieldKind> {
+    let kind = match property.get("type") {
+        Some(kind) => kind.as_str().unwrap_or_default(),
+        None => match property.get("enum").and_then(Value::as_array).and_then(|values| values.first()) {
+            Some(Value::String(_)) => "string",
+            Some(Value::Bool(_)) => "boolean",
+            Some(Value::Number(number)) if number.is_i64() || number.is_u64() => "integer",
+            Some(Value::Number(_)) => "number",
+            _ => "",
+        },
+    };
+    match kind {
+        "string" => Ok(FormFieldKind::String),
+        "number" => Ok(FormFieldKind::Number),
+        "integer" => Ok(FormFieldKind::Integer),
+        "boolean" => Ok(FormFieldKind::Boolean),
+        "array" => Ok(FormFieldKind::Array),
+        "" => anyhow::bail!("缺少 type"),
+        other => anyhow::bail!("不支援的類型 {}", other),
+    }
+}
+
+/// 依欄位定義驗證使用者送出的值
+///
+/// 未填寫的欄位套用預設值，沒有預設值的選填欄位省略；Schema 中沒有的欄位會被忽略。
+/// 驗證失敗時傳回每個欄位的錯誤訊息
+pub fn validate_form_values(fields: &[FormField], values: &Value) -> Result<Map<String, Value>, Vec<String>> {
+    let empty = Map::new();
+    let values = match values {
+        Value::Object(values) => values,
+        Value::Null => &empty,
+        _ => return Err(vec!["表單值必須是 JSON 物件".to_string()]),
+    };
+
+    let mut result = Map::new();
+    let mut errors = Vec::new();
+    for field in fields {
+        match validate_field(field, values.get(&field.name)) {
+            Ok(Some(value)) => {
+                result.insert(field.name.clone(), value);
+            }
+            Ok(None) => {}
+            Err(e) => errors.push(format!("{}：{}", field.name, e)),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(result)
+    } else {
+        Err(errors)
+    }
+}
+
+/// 驗證單一欄位，傳回要寫入結果的值；選填且未填寫時傳回 None
+pub fn validate_field(field: &FormField, value: Option<&Value>) -> Result<Option<Value>, String> {
+    // null、空字串与必填欄位的空陣列都視為未填寫
+    let value = value.filter(|value| match value {
+        Value::Null => false,
+        Value::String(text) => !text.is_empty(),
+        Value::Array(items) => !(items.is_empty() && field.required),
+        _ => true,
+    });
+
+    match (value, &field.default) {
+        (Some(value), _) => validate_value(field, value).map(Some),
+        (None, Some(default)) => Ok(Some(default.clone())),
+        (None, None) if field.required => Err("必填".to_string()),
+        (None, None) => Ok(None),
+    }
+}
+
+fn validate_value(field: &FormField, value: &Value) -> Result<Value, String> {
+    if field.kind != FormFieldKind::Array {
+        return validate_element(field, field.kind, value);
+    }
+
+    let Value::Array(items) = value else {
+        return Err(format!("應為陣列，收到 {}", value));
+    };
+    if let Some(min) = field.min_items.filter(|min| items.len() < *min) {
+        return Err(format!("至少需要 {} 項", min));
+    }
+    if let Some(max) = field.max_items.filter(|max| items.len() > *max) {
+        return Err(format!("最多 {} 項", max));
+    }
+    let item_kind = field.item_kind.unwrap_or(FormFieldKind::String);
+    items
+        .iter()
+        .enumerate()
+        .map(|(index, item)| validate_element(field, item_kind, item).map_err(|e| format!("第 {} 項{}", index + 1, e)))
+        .collect::<Result<Vec<_>, _>>()
+        .map(Value::Array)
+}
+
+/// 檢查值是否符合類型
+fn check_kind(kind: FormFieldKind, value: &Value) -> Result<(), String> {
+    let matched = match kind {
+        FormFieldKind::String => value.is_string(),
+        FormFieldKind::Number => value.is_number(),
+        // 允許 3.0 這類沒有小數部分的數字
+        FormFieldKind::Integer => value.as_f64().is_some_and(|number| number.fract() == 0.0),
+        FormFieldKind::Boolean => value.is_boolean(),
+        FormFieldKind::Array => value.is_array(),
+    };
+    if matched {
+        Ok(())
+    } else {
+        Err(format!("應為 {}，收到 {}", kind_name(kind), value))
+    }
+}
+
+/// 驗證單一值（一般欄位的值或陣列元素）
+fn validate_element(field: &FormField, kind: FormFieldKind, value: &Value) -> Result<Value, String> {
+    check_kind(kind, value)?;
+
+    if !field.options.is_empty() && !field.options.iter().any(|option| same_value(&option.value, value)) {
+        let allowed: Vec<String> = field.options.iter().map(|option| display_value(&option.value)).collect();
+        return Err(format!("必須是 {} 之一", allowed.join("、")));
+    }
+
+    match kind {
+        FormFieldKind::Number | FormFieldKind::Integer => {
+            let number = value.as_f64().unwrap_or_default();
+            if let Some(min) = field.minimum.filter(|min| number < *min) {
+                return Err(format!("不能小於 {}", min));
+            }
+            if let Some(max) = field.maximum.filter(|max| number > *max) {
+                return Err(format!("不能大於 {}", max));
+            }
+            if kind == FormFieldKind::Integer {
+                return Ok(Value::from(number as i64));
+            }
+        }
+        FormFieldKind::String => {
+            let text = value.as_str().unwrap_or_default();
+            let length = text.chars().count();
+            if let Some(min) = field.min_length.filter(|min| length < *min) {
+                return Err(format!("長度至少 {} 個字元", min));
+            }
+            if let Some(max) = field.max_length.filter(|max| length > *max) {
+                return Err(format!("長度最多 {} 個字元", max));
+            }
+            if let Some(pattern) = &field.pattern {
+                let matched = regex::Regex::new(pattern).map(|re| re.is_match(text)).unwrap_or(true);
+                if !matched {
+                    return Err(format!("格式不符合 {}", pattern));
+                }
+            }
+        }
+        FormFieldKind::Boolean | FormFieldKind::Array => {}
+    }
+    Ok(value.clone())
+}
+
+/// 比較列舉值，數字以數值比較（1 与 1.0 視為相同）
+fn same_value(a: &Value, b: &Value) -> bool {
+    match (a.as_f64(), b.as_f64()) {
+        (Some(a), Some(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// 字串顯示本身，其他值顯示 JSON
+pub fn display_value(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn kind_name(kind: FormFieldKind) -> &'static str {
+    match kind {
+        FormFieldKind::String => "字串",
+        FormFieldKind::Number => "數字",
+        FormFieldKind::Integer => "整數",
+        FormFieldKind::Boolean => "布林值",
+        FormFieldKind::Array => "陣列",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn sample_schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "port": { "type": "integer", "minimum": 1, "maximum": 65535, "default": 8080 },
+                "name": { "type": "string", "title": "服務名稱", "pattern": "^[a-z-]+$" },
+                "env": { "enum": ["dev", "prod"] },
+                "features": { "type": "array", "items": { "type": "string", "enum": ["auth", "cache"] } },
+                "debug": { "type": "boolean" }
+            },
+            "required": ["name", "env"]
+        })
+    }
+
+    #[test]
+    fn test_parse_form_schema() {
+        let fields = parse_form_schema(&sample_schema()).unwrap();
+        let names: Vec<&str> = fields.iter().map(|field| field.name.as_str()).collect();
+        assert_eq!(names, vec!["port", "name", "env", "features", "debug"]);
+        assert_eq!(fields[1].title, "服務名稱");
+        assert!(fields[1].required && !fields[0].required);
+        assert_eq!(fields[2].kind, FormFieldKind::String);
+        assert_eq!(fields[3].item_kind, Some(FormFieldKind::String));
+        assert_eq!(fields[3].options.len(), 2);
+
+        assert!(parse_form_schema(&json!({ "type": "object", "properties": {} })).is_err());
+        assert!(parse_form_schema(&json!({ "properties": { "a": { "type": "object" } } })).is_err());
+        assert!(parse_form_schema(&json!({ "properties": { "a": { "type": "integer", "default": "x" } } })).is_err());
+    }
+
+    #[test]
+    fn test_validate_form_values() {
+        let fields = parse_form_schema(&sample_schema()).unwrap();
+
+        let values = validate_form_values(&fields, &json!({ "name": "api", "env": "prod", "extra": 1 })).unwrap();
+        assert_eq!(Value::Object(values), json!({ "port": 8080, "name": "api", "env": "prod" }));
+
+        let errors = validate_form_values(
+            &fields,
+            &json!({ "port": 70000, "name": "API", "env": "test", "features": ["auth", "x"], "debug": "yes" }),
+        )
+        .unwrap_err();
+        assert_eq!(errors.len(), 5);
+
+        let errors = validate_form_values(&fields, &json!({ "name": "" })).unwrap_err();
+        assert_eq!(errors, vec!["name：必填", "env：必填"]);
+    }
+}
@@ -0,0 +1,108 @@
+//! 設定目錄下以 ID 命名的 JSON 檔案
+//!
+//! 票證与通知日誌都是「一個 ID 一個 JSON 檔」，共用 ID 檢查、原子寫入与過期清理
+
+use anyhow::Result;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::config::get_standalone_config_path;
+use crate::log_debug;
+
+/// 一個存放 JSON 檔案的目錄
+pub struct JsonDir {
+    dir: PathBuf,
+}
+
+impl JsonDir {
+    /// 開啟設定目錄下的子目錄，不存在時建立
+    pub fn open(name: &str) -> Result<Self> {
+        Self::new(get_standalone_config_path()?.with_file_name(name))
+    }
+
+    /// 開啟指定目錄，不存在時建立
+    pub fn new(dir: PathBuf) -> Result<Self> {
+        fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+
+    /// ID 對應的檔案路徑，ID 只允許英數字与 `-`，避免路徑穿越
+    pub fn path(&self, id: &str) -> Result<PathBuf> {
+        if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            anyhow::bail!("無效的 ID: {}", id);
+        }
+        Ok(self.dir.join(format!("{}.json", id)))
+    }
+
+    /// 讀取 ID 對應的檔案，不存在時傳回 None
+    pub fn load<T: DeserializeOwned>(&self, id: &str) -> Result<Option<T>> {
+        let path = self.path(id)?;
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(&path)?;
+        let value = serde_json::from_str(&content).map_err(|e| anyhow::anyhow!("{} 格式錯誤: {}", id, e))?;
+        Ok(Some(value))
+    }
+
+    /// 寫入 ID 對應的檔案，先寫暫存檔再改名，避免其他程序讀到寫了一半的內容
+    pub fn save<T: Serialize>(&self, id: &str, value: &T) -> Result<()> {
+        let path = self.path(id)?;
+        let temp_path = path.with_extension("json.tmp");
+        fs::write(&temp_path, serde_json::to_string_pretty(value)?)?;
+        fs::rename(&temp_path, &path)?;
+        Ok(())
+    }
+
+    /// 讀取目錄中所有的檔案，無法解析的略過
+    pub fn load_all<T: DeserializeOwned>(&self) -> Result<Vec<T>> {
+        let values = fs::read_dir(&self.dir)?
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .filter_map(|path| {
+                let content = fs::read_to_string(&path).ok()?;
+                match serde_json::from_str(&content) {
+                    Ok(value) => Some(value),
+                    Err(e) => {
+                        log_debug!("JSON 檔案格式錯誤: {:?}, 錯誤: {}", path, e);
+                        None
+                    }
+                }
+            })
+            .collect();
+        Ok(values)
+    }
+
+    /// 刪除目錄中所有的檔案
+    pub fn clear(&self) -> Result<()> {
+        for entry in fs::read_dir(&self.dir)?.flatten() {
+            fs::remove_file(entry.path())?;
+        }
+        Ok(())
+    }
+
+    /// 刪除超過保留秒數未更新的檔案
+    pub fn cleanup_expired(&self, retention_secs: u64) {
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return;
+        };
+        let now = std::time::SystemTime::now();
+        for entry in entries.flatten() {
+            let expired = entry
+                .metadata()
+                .and_then(|metadata| metadata.modified())
+                .ok()
+                .and_then(|modified| now.duration_since(modified).ok())
+                .is_some_and(|age| age.as_secs() > retention_secs);
+            if expired {
+                match fs::remove_file(entry.path()) {
+                    Ok(_) => log_debug!("已清理過期檔案: {:?}", entry.path()),
+                    Err(e) => log_debug!("清理過期檔案失敗: {:?}, 錯誤: {}", entry.path(), e),
+                }
+            }
+        }
+    }
+}
@@ -1,5 +1,17 @@
+pub mod attachment;
 pub mod common;
+pub mod context;
+pub mod diff;
 pub mod errors;
+pub mod form;
+pub mod json_dir;
+pub mod schema;
 
+pub use attachment::*;
 pub use common::*;
+pub use context::*;
+pub use diff::*;
 pub use errors::*;
+pub use form::*;
+pub use json_dir::*;
+pub use schema::*;
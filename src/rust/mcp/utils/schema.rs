@@ -0,0 +1,28 @@
+//! MCP 工具 Schema 生成模組
+//!
+//! 由 Rust 請求/回應類型推導 JSON Schema，避免手寫 Schema 與類型不同步
+
+use rmcp::model::JsonObject;
+use schemars::gen::SchemaSettings;
+use schemars::JsonSchema;
+use std::sync::Arc;
+
+/// 生成指定類型的 JSON Schema 物件
+///
+/// 子 Schema 會被內聯展開，並移除 `$schema`、`title` 等客戶端不需要的欄位
+pub fn schema_object_for<T: JsonSchema>() -> Arc<JsonObject> {
+    let settings = SchemaSettings::draft07().with(|settings| {
+        settings.inline_subschemas = true;
+        settings.meta_schema = None;
+    });
+    let root_schema = settings.into_generator().into_root_schema_for::<T>();
+
+    let mut schema = match serde_json::to_value(root_schema) {
+        Ok(serde_json::Value::Object(map)) => map,
+        _ => JsonObject::new(),
+    };
+    schema.remove("title");
+    schema.remove("definitions");
+
+    Arc::new(schema)
+}
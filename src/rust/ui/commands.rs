@@ -1,8 +1,9 @@
 use crate::config::{save_config, load_config as load_config_from_file, AppState, ReplyConfig, WindowConfig, CustomPrompt, CustomPromptConfig, ShortcutConfig, ShortcutBinding, AppConfig};
 use crate::constants::{window, ui, validation};
-use crate::mcp::types::{build_continue_response, build_send_response, ImageAttachment, PopupRequest};
-use crate::mcp::handlers::create_tauri_popup;
+use crate::mcp::types::{build_continue_response, FileReview, ImageAttachment, McpResponse, PopupRequest, PredefinedOption, QuestionAnswer};
+use crate::mcp::handlers::{create_tauri_popup, PopupOutcome};
 use tauri::{AppHandle, Manager, State};
+use tokio_util::sync::CancellationToken;
 
 #[tauri::command]
 pub async fn get_app_info() -> Result<String, String> {
@@ -411,11 +412,14 @@ pub async fn set_window_settings(
 #[tauri::command]
 pub async fn send_mcp_response(
     response: serde_json::Value,
+    request_id: Option<String>,
     state: State<'_, AppState>,
 ) -> Result<(), String> {
-    // 将回應序列化为JSON字符串
-    let response_str =
-        serde_json::to_string(&response).map_err(|e| format!("序列化回應失敗: {}", e))?;
+    // 将回應序列化为JSON字符串，纯文字回應（如 CANCELLED）直接使用原文
+    let response_str = match &response {
+        serde_json::Value::String(text) => text.clone(),
+        _ => serde_json::to_string(&response).map_err(|e| format!("序列化回應失敗: {}", e))?,
+    };
 
     if response_str.trim().is_empty() {
         return Err("回應內容不能为空".to_string());
@@ -431,13 +435,27 @@ pub async fn send_mcp_response(
         std::io::Write::flush(&mut std::io::stdout())
             .map_err(|e| format!("重新整理stdout失敗: {}", e))?;
     } else {
-        // 透過channel傳送回應（如果有的话）
+        // 依 request_id 透過channel傳回給對應的請求，未指定時從回應的 metadata 取得
+        let request_id = request_id.or_else(|| {
+            response
+                .pointer("/metadata/request_id")
+                .and_then(|id| id.as_str())
+                .map(|id| id.to_string())
+        });
         let sender = {
-            let mut channel = state
-                .response_channel
+            let mut channels = state
+                .response_channels
                 .lock()
                 .map_err(|e| format!("獲取回應通道失敗: {}", e))?;
-            channel.take()
+            match request_id {
+                Some(id) => channels.remove(&id),
+                // 只有一個等待中的請求時，無 ID 的回應就屬於它
+                None if channels.len() == 1 => {
+                    let id = channels.keys().next().cloned();
+                    id.and_then(|id| channels.remove(&id))
+                }
+                None => None,
+            }
         };
 
         if let Some(sender) = sender {
@@ -523,6 +541,12 @@ pub async fn open_external_url(url: String) -> Result<(), String> {
 
 #[tauri::command]
 pub async fn exit_app(app: AppHandle) -> Result<(), String> {
+    // 常駐模式下回應后不退出，沒有其他待回應請求時隱藏視窗
+    if crate::app::daemon::is_daemon_mode() {
+        crate::app::daemon::hide_if_idle(&app);
+        return Ok(());
+    }
+
     // 直接呼叫強制退出，用于程序内部的退出操作（如MCP回應后退出）
     crate::ui::exit::force_exit_app(app).await
 }
@@ -539,18 +563,21 @@ pub async fn handle_app_exit_request(app: AppHandle) -> Result<bool, String> {
 #[tauri::command]
 pub fn build_mcp_send_response(
     user_input: Option<String>,
-    selected_options: Vec<String>,
+    selected_options: Vec<PredefinedOption>,
     images: Vec<ImageAttachment>,
+    answers: Option<Vec<QuestionAnswer>>,
+    review: Option<Vec<FileReview>>,
     request_id: Option<String>,
     source: String,
 ) -> Result<String, String> {
-    Ok(build_send_response(
+    let response = McpResponse {
         user_input,
-        selected_options,
         images,
-        request_id,
-        &source,
-    ))
+        answers: answers.unwrap_or_default(),
+        review: review.unwrap_or_default(),
+        ..McpResponse::new(request_id, &source)
+    };
+    Ok(response.with_selected_options(selected_options).to_string())
 }
 
 /// 建構繼續操作的MCP回應
@@ -570,8 +597,10 @@ pub async fn create_test_popup(request: serde_json::Value) -> Result<String, Str
         .map_err(|e| format!("解析請求參數失敗: {}", e))?;
 
     // 呼叫现有的popup建立函數
-    match create_tauri_popup(&popup_request) {
-        Ok(response) => Ok(response),
+    match create_tauri_popup(&popup_request, &CancellationToken::new()).await {
+        Ok(PopupOutcome::Response(response)) => Ok(response),
+        Ok(PopupOutcome::Cancelled) => Err("測試popup已取消".to_string()),
+        Ok(PopupOutcome::TimedOut) => Err("測試popup等待逾時".to_string()),
         Err(e) => Err(format!("建立測試popup失敗: {}", e))
     }
 }
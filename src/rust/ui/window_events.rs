@@ -11,6 +11,12 @@ pub fn setup_window_event_listeners(app_handle: &AppHandle) {
             if let WindowEvent::CloseRequested { api, .. } = event {
                 // 阻止預設的關閉行为
                 api.prevent_close();
+
+                // 常駐模式下關閉視窗只隱藏，程序保留在系統匣
+                if crate::app::daemon::is_daemon_mode() {
+                    crate::app::daemon::hide_main_window(&app_handle_clone);
+                    return;
+                }
                 
                 let app_handle = app_handle_clone.clone();
                 
@@ -0,0 +1,150 @@
+//! 寸止与常駐等一下之間的本機 IPC
+//!
+//! Unix 使用 Unix domain socket，Windows 使用 named pipe；
+//! 訊息為以換行分隔的 JSON，每個連線處理一個請求。
+//! Unix 的 socket 放在只有目前使用者能存取的目錄，連線兩端都會確認對方是同一個使用者
+
+use anyhow::Result;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::mcp::types::{Notification, PopupRequest};
+
+/// 寸止傳送給常駐程序的訊息
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DaemonMessage {
+    /// 顯示弹窗並等待使用者回應
+    Popup { request: PopupRequest },
+    /// 顯示進度通知，不需要回覆
+    Notify { notification: Notification },
+}
+
+/// 常駐程序的回覆
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DaemonReply {
+    /// 与 `--mcp-request` 模式輸出到 stdout 相同的回應內容
+    pub response: String,
+}
+
+/// 獲取 IPC 端點位置
+///
+/// 優先使用 XDG runtime 目錄，否則使用設定目錄旁的 `ipc` 子目錄；
+/// 不使用所有使用者共用的暫存目錄，避免其他使用者搶先建立同名的 socket
+#[cfg(unix)]
+pub fn daemon_endpoint() -> Result<std::path::PathBuf> {
+    let dir = match dirs::runtime_dir() {
+        Some(dir) => dir.join("cunzhi"),
+        None => crate::config::get_standalone_config_path()?.with_file_name("ipc"),
+    };
+    ensure_private_dir(&dir)?;
+    Ok(dir.join("daemon.sock"))
+}
+
+/// 建立只有目前使用者能存取（0700）的目錄；已存在時確認屬於目前使用者並收緊權限
+#[cfg(unix)]
+fn ensure_private_dir(dir: &std::path::Path) -> Result<()> {
+    use std::os::unix::fs::{DirBuilderExt, MetadataExt, PermissionsExt};
+
+    if let Some(parent) = dir.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    match std::fs::DirBuilder::new().mode(0o700).create(dir) {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {}
+        Err(e) => return Err(e.into()),
+    }
+
+    // 不跟隨符號連結，避免被導向其他使用者的目錄
+    let metadata = std::fs::symlink_metadata(dir)?;
+    if !metadata.is_dir() || metadata.uid() != current_uid() {
+        anyhow::bail!("IPC 目錄不屬於目前使用者: {:?}", dir);
+    }
+    if metadata.mode() & 0o077 != 0 {
+        std::fs::set_permissions(dir, std::fs::Permissions::from_mode(0o700))?;
+    }
+    Ok(())
+}
+
+/// 目前程序的有效使用者 ID
+#[cfg(unix)]
+fn current_uid() -> u32 {
+    // SAFETY: geteuid 沒有前置條件且不會失敗
+    unsafe { libc::geteuid() }
+}
+
+/// 確認連線另一端的程序屬於目前使用者
+#[cfg(unix)]
+pub fn verify_peer(stream: &tokio::net::UnixStream) -> Result<()> {
+    let uid = stream.peer_cred()?.uid();
+    if uid != current_uid() {
+        anyhow::bail!("IPC 連線的另一端屬於其他使用者 (uid {})", uid);
+    }
+    Ok(())
+}
+
+/// 獲取 IPC 端點位置
+#[cfg(windows)]
+pub fn daemon_endpoint() -> String {
+    let user = std::env::var("USERNAME").unwrap_or_else(|_| "default".to_string());
+    format!(r"\\.\pipe\cunzhi-{}", user)
+}
+
+/// 連線到常駐程序，未執行或不屬於目前使用者時傳回錯誤
+#[cfg(unix)]
+pub async fn connect_daemon() -> Result<tokio::net::UnixStream> {
+    let stream = tokio::net::UnixStream::connect(daemon_endpoint()?).await?;
+    verify_peer(&stream)?;
+    Ok(stream)
+}
+
+/// 連線到常駐程序，未執行時傳回錯誤
+#[cfg(windows)]
+pub async fn connect_daemon() -> Result<tokio::net::windows::named_pipe::NamedPipeClient> {
+    Ok(tokio::net::windows::named_pipe::ClientOptions::new().open(daemon_endpoint())?)
+}
+
+/// 寫入一則訊息
+pub async fn write_message<T: Serialize>(writer: &mut (impl AsyncWrite + Unpin), message: &T) -> Result<()> {
+    let mut line = serde_json::to_string(message)?;
+    line.push('\n');
+    writer.write_all(line.as_bytes()).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+/// 讀取一則訊息，對方關閉連線時傳回 None
+pub async fn read_message<T: DeserializeOwned>(reader: &mut (impl AsyncBufRead + Unpin)) -> Result<Option<T>> {
+    let mut line = String::new();
+    if reader.read_line(&mut line).await? == 0 {
+        return Ok(None);
+    }
+    Ok(Some(serde_json::from_str(line.trim())?))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn test_private_dir_permissions() {
+        let dir = std::env::temp_dir().join(format!("cunzhi-ipc-{}", std::process::id()));
+        ensure_private_dir(&dir).unwrap();
+        let mode = |dir: &std::path::Path| std::fs::metadata(dir).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode(&dir), 0o700);
+
+        // 已存在但權限過寬的目錄會被收緊
+        std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o755)).unwrap();
+        ensure_private_dir(&dir).unwrap();
+        assert_eq!(mode(&dir), 0o700);
+
+        // 指向目錄的符號連結不被接受
+        let link = dir.with_extension("link");
+        std::os::unix::fs::symlink(&dir, &link).unwrap();
+        assert!(ensure_private_dir(&link).is_err());
+
+        std::fs::remove_file(&link).unwrap();
+        std::fs::remove_dir(&dir).unwrap();
+    }
+}
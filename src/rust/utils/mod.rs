@@ -1,3 +1,4 @@
+pub mod ipc;
 pub mod logger;
 
 pub use logger::{LogConfig, init_logger, auto_init_logger};